@@ -1,39 +1,240 @@
+use std::cell::Cell;
 use std::fmt::Display;
+use std::num::NonZeroU64;
+use std::rc::Rc;
+use std::str::FromStr;
 
-use bigdecimal::{BigDecimal, FromPrimitive, Num};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, Context, Num, One, Signed, ToPrimitive, Zero};
 use napi_derive_ohos::napi;
-use napi_ohos::bindgen_prelude::{Either3, Function, JavaScriptClassExt};
-use napi_ohos::{Env, Error, JsNumber, JsObject, JsUnknown, Result, Status};
+use napi_ohos::bindgen_prelude::{
+    Either, Either3, FromNapiValue, Function, JavaScriptClassExt, ToNapiValue,
+};
+use napi_ohos::{
+    CallContext, Env, Error, JsFunction, JsNumber, JsObject, JsUnknown, NapiRaw, NapiValue, Result,
+    Status,
+};
 
+/// A decimal number with the config of the constructor that made it, or, if
+/// the `f64` is given, `NaN`, `Infinity` or `-Infinity`.
 #[napi]
 #[derive(Debug, Clone)]
-pub struct BigNumber(pub(crate) BigDecimal);
+pub struct BigNumber(pub(crate) BigDecimal, SharedConfig, pub(crate) Option<f64>);
+
+/// How a result is rounded to the digits it keeps, named like the rounding
+/// modes of bignumber.js.
+#[napi(string_enum = "UPPER_SNAKE")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Away from zero.
+    RoundUp,
+    /// Towards zero.
+    RoundDown,
+    /// Towards `Infinity`.
+    RoundCeil,
+    /// Towards `-Infinity`.
+    RoundFloor,
+    /// To the nearest neighbour, or away from zero if both are as near.
+    RoundHalfUp,
+    /// To the nearest neighbour, or towards zero if both are as near.
+    RoundHalfDown,
+    /// To the nearest neighbour, or the even one if both are as near.
+    RoundHalfEven,
+    /// To the nearest neighbour, or towards `Infinity` if both are as near.
+    RoundHalfCeil,
+    /// To the nearest neighbour, or towards `-Infinity` if both are as near.
+    RoundHalfFloor,
+}
+
+/// The settings of `BigNumber`, named like those of bignumber.js. Each worker
+/// has its own.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct BigNumberConfig {
+    /// Decimal places of the results that can't be exact, e.g. `1 / 3` or
+    /// `2^-0.5`, from 0 to 1e9. 20 by default.
+    #[napi(js_name = "DECIMAL_PLACES")]
+    pub decimal_places: Option<u32>,
+    /// How those results are rounded, and the rounding of the methods that
+    /// take a `RoundingMode` when none is given. `ROUND_HALF_UP` by default.
+    #[napi(js_name = "ROUNDING_MODE")]
+    pub rounding_mode: Option<RoundingMode>,
+    /// The exponents at which `toString` switches to scientific notation: at
+    /// or below the first and at or above the second, or at or beyond `n` and
+    /// `-n` for a number `n`. `[-7, 20]` by default, so `12.5` stays `12.5`.
+    #[napi(js_name = "EXPONENTIAL_AT", ts_type = "number | [number, number]")]
+    pub exponential_at: Option<Either<u32, Vec<i64>>>,
+    /// The exponents beyond which results don't go, given like
+    /// `EXPONENTIAL_AT`. `[-1e9, 1e9]` by default. A result with an exponent
+    /// below the first is zero, and one with an exponent above the second is
+    /// `Infinity` or `-Infinity`.
+    #[napi(js_name = "RANGE", ts_type = "number | [number, number]")]
+    pub range: Option<Either<u32, Vec<i64>>>,
+}
+
+/// The most `DECIMAL_PLACES`, and exponent magnitude of `EXPONENTIAL_AT` and
+/// `RANGE`, allowed, as in bignumber.js.
+const MAX_DECIMAL_PLACES: u32 = 1_000_000_000;
+
+/// The settings in effect, from `BigNumber.config`.
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    decimal_places: i64,
+    rounding_mode: RoundingMode,
+    /// The negative and positive exponent of `EXPONENTIAL_AT`.
+    exponential_at: (i64, i64),
+    /// The negative and positive exponent of `RANGE`.
+    range: (i64, i64),
+}
+
+impl Config {
+    const DEFAULT: Config = Config {
+        decimal_places: 20,
+        rounding_mode: RoundingMode::RoundHalfUp,
+        exponential_at: (-7, 20),
+        range: (-(MAX_DECIMAL_PLACES as i64), MAX_DECIMAL_PLACES as i64),
+    };
+
+    /// This config with the settings given in `config` changed.
+    fn with(mut self, config: BigNumberConfig) -> Result<Config> {
+        if let Some(dp) = config.decimal_places {
+            if dp > MAX_DECIMAL_PLACES {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    format!("DECIMAL_PLACES must be at most {MAX_DECIMAL_PLACES}"),
+                ));
+            }
+            self.decimal_places = dp as i64;
+        }
+        if let Some(rm) = config.rounding_mode {
+            self.rounding_mode = rm;
+        }
+        if let Some(at) = config.exponential_at {
+            self.exponential_at = exponent_range("EXPONENTIAL_AT", at)?;
+        }
+        if let Some(range) = config.range {
+            self.range = exponent_range("RANGE", range)?;
+        }
+        Ok(self)
+    }
+
+    fn to_js(self) -> BigNumberConfig {
+        let (at_neg, at_pos) = self.exponential_at;
+        let (min, max) = self.range;
+        BigNumberConfig {
+            decimal_places: Some(self.decimal_places as u32),
+            rounding_mode: Some(self.rounding_mode),
+            exponential_at: Some(Either::B(vec![at_neg, at_pos])),
+            range: Some(Either::B(vec![min, max])),
+        }
+    }
+}
+
+thread_local! {
+    /// The config of `BigNumber` on this thread. A worker loads the module, and
+    /// so gets a `BigNumber` class, of its own.
+    static DEFAULT_CONFIG: SharedConfig = SharedConfig::new(Config::DEFAULT);
+}
+
+/// The config a BigNumber follows, that of the constructor that made it. The
+/// default is that of `BigNumber`. The config of a clone is shared by it and
+/// the BigNumbers it made, and freed with the last of them.
+#[derive(Debug, Clone)]
+struct SharedConfig(Rc<Cell<Config>>);
+
+impl Default for SharedConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG.with(SharedConfig::clone)
+    }
+}
+
+impl SharedConfig {
+    fn new(config: Config) -> Self {
+        SharedConfig(Rc::new(Cell::new(config)))
+    }
+
+    fn get(&self) -> Config {
+        self.0.get()
+    }
+
+    /// Changes the settings given in `config` and returns all of them.
+    fn configure(&self, config: Option<BigNumberConfig>) -> Result<BigNumberConfig> {
+        let mut current = self.get();
+        if let Some(config) = config {
+            current = current.with(config)?;
+            self.0.set(current);
+        }
+        Ok(current.to_js())
+    }
+
+    /// `x` as a BigNumber with this config, which underflows to zero and
+    /// overflows to `Infinity` or `-Infinity` beyond `RANGE`.
+    fn in_range(&self, x: BigDecimal) -> BigNumber {
+        let e = exponent(&x) as i128;
+        match self.beyond_range(e, e, x.is_negative()) {
+            Some(result) => result,
+            None => BigNumber(x, self.clone(), None),
+        }
+    }
+
+    /// What a result with an exponent from `low` to `high`, and negative if
+    /// `negative`, is beyond `RANGE`, or `None` if it may be in range.
+    fn beyond_range(&self, low: i128, high: i128, negative: bool) -> Option<BigNumber> {
+        let (min, max) = self.get().range;
+        if low > max as i128 {
+            let infinity = if negative {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            };
+            return Some(self.from_f64(infinity));
+        }
+        if high < min as i128 {
+            return Some(BigNumber(BigDecimal::zero(), self.clone(), None));
+        }
+        None
+    }
+
+    /// `x` as a BigNumber with this config, with the digits of `String(x)` as
+    /// in bignumber.js.
+    fn from_f64(&self, x: f64) -> BigNumber {
+        if !x.is_finite() {
+            return BigNumber(BigDecimal::zero(), self.clone(), Some(x));
+        }
+        let mut buffer = ryu_js::Buffer::new();
+        self.in_range(BigDecimal::from_str(buffer.format_finite(x)).unwrap())
+    }
+}
+
+impl RoundingMode {
+    /// `x` rounded to `dp` decimal places, which may be negative to round to
+    /// tens, hundreds, etc.
+    pub fn round(self, x: &BigDecimal, dp: i64) -> BigDecimal {
+        use bigdecimal::RoundingMode as Mode;
+
+        let mode = match self {
+            RoundingMode::RoundUp => Mode::Up,
+            RoundingMode::RoundDown => Mode::Down,
+            RoundingMode::RoundCeil => Mode::Ceiling,
+            RoundingMode::RoundFloor => Mode::Floor,
+            RoundingMode::RoundHalfUp => Mode::HalfUp,
+            RoundingMode::RoundHalfDown => Mode::HalfDown,
+            RoundingMode::RoundHalfEven => Mode::HalfEven,
+            // Towards `Infinity` is away from zero for a positive number.
+            RoundingMode::RoundHalfCeil if x.is_negative() => Mode::HalfDown,
+            RoundingMode::RoundHalfCeil => Mode::HalfUp,
+            RoundingMode::RoundHalfFloor if x.is_negative() => Mode::HalfUp,
+            RoundingMode::RoundHalfFloor => Mode::HalfDown,
+        };
+        x.with_scale_round(dp, mode)
+    }
+}
 
 #[napi]
 impl BigNumber {
     #[napi(constructor)]
     pub fn new(n: Either3<JsNumber, String, &BigNumber>, base: Option<u32>) -> Result<Self> {
-        match n {
-            Either3::A(n) => {
-                if let Ok(n) = n.get_int64() {
-                    Ok(BigNumber(BigDecimal::from_i64(n).unwrap()))
-                } else if let Ok(n) = n.get_int32() {
-                    Ok(BigNumber(BigDecimal::from_i32(n).unwrap()))
-                } else if let Ok(n) = n.get_uint32() {
-                    Ok(BigNumber(BigDecimal::from_u32(n).unwrap()))
-                } else if let Ok(n) = n.get_double() {
-                    Ok(BigNumber(BigDecimal::from_f64(n).unwrap()))
-                } else {
-                    Err(Error::new(Status::InvalidArg, "invalid number"))
-                }
-            }
-            Either3::B(s) => {
-                let n = BigDecimal::from_str_radix(s.as_str(), base.unwrap_or(10))
-                    .map_err(|e| Error::new(Status::InvalidArg, e))?;
-                Ok(BigNumber(n))
-            }
-            Either3::C(n) => Ok(n.clone()),
-        }
+        BigNumber::parse(n, base, &SharedConfig::default())
     }
 
     #[napi]
@@ -41,142 +242,707 @@ impl BigNumber {
         BigNumber::instance_of(env, &value).is_ok_and(|v| v)
     }
 
+    /// Changes the settings given in `config` and returns all of them.
+    #[napi]
+    pub fn config(config: Option<BigNumberConfig>) -> Result<BigNumberConfig> {
+        SharedConfig::default().configure(config)
+    }
+
+    /// A constructor like `BigNumber` with a config of its own, the defaults
+    /// changed by `config`. It makes `BigNumber`s, which are its instances,
+    /// and has its own `config`, `clone` and `isBigNumber`.
+    #[napi(js_name = "clone", ts_return_type = "typeof BigNumber")]
+    pub fn clone_constructor(env: Env, config: Option<BigNumberConfig>) -> Result<JsObject> {
+        let config = SharedConfig::new(match config {
+            Some(config) => Config::DEFAULT.with(config)?,
+            None => Config::DEFAULT,
+        });
+        let shared = config.clone();
+        let mut constructor = env
+            .create_function_from_closure("BigNumber", move |ctx: CallContext<'_>| {
+                let n = ctx.get::<Either3<JsNumber, String, &BigNumber>>(0)?;
+                BigNumber::parse(n, optional_arg(&ctx, 1)?, &shared)
+            })?
+            .coerce_to_object()?;
+        // Its BigNumbers have the methods of `BigNumber.prototype`, taken from
+        // a BigNumber. `instanceof` would find that for any BigNumber, so its
+        // `Symbol.hasInstance` checks for its config instead.
+        let instance =
+            unsafe { BigNumber::to_napi_value(env.raw(), BigNumber::from(BigDecimal::zero()))? };
+        let instance = unsafe { JsObject::from_raw(env.raw(), instance)? };
+        constructor.set_named_property("prototype", instance.get_prototype::<JsObject>()?)?;
+        let shared = config.clone();
+        let has_instance: JsFunction = env.create_function_from_closure(
+            "[Symbol.hasInstance]",
+            move |ctx: CallContext<'_>| {
+                let value: Option<JsUnknown> = optional_arg(&ctx, 0)?;
+                let Some(value) = value else {
+                    return Ok(false);
+                };
+                if !BigNumber::instance_of(*ctx.env, &value)? {
+                    return Ok(false);
+                }
+                let value: &BigNumber =
+                    unsafe { FromNapiValue::from_napi_value(ctx.env.raw(), value.raw())? };
+                Ok(Rc::ptr_eq(&value.1.0, &shared.0))
+            },
+        )?;
+        let global = env.get_global()?;
+        let symbol: JsObject = global.get_named_property("Symbol")?;
+        let object: JsObject = global.get_named_property("Object")?;
+        let define_property: JsFunction = object.get_named_property("defineProperty")?;
+        let mut descriptor = env.create_object()?;
+        descriptor.set_named_property("value", has_instance)?;
+        // Assigning it would fail, as `Function.prototype[Symbol.hasInstance]`
+        // is not writable.
+        define_property.call(
+            Some(&object),
+            &[
+                constructor.into_unknown(),
+                symbol.get_named_property::<JsUnknown>("hasInstance")?,
+                descriptor.into_unknown(),
+            ],
+        )?;
+        let configure: JsFunction = env
+            .create_function_from_closure("config", move |ctx: CallContext<'_>| {
+                config.configure(optional_arg(&ctx, 0)?)
+            })?;
+        constructor.set_named_property("config", configure)?;
+        let clone: JsFunction = env
+            .create_function_from_closure("clone", |ctx: CallContext<'_>| {
+                BigNumber::clone_constructor(*ctx.env, optional_arg(&ctx, 0)?)
+            })?;
+        constructor.set_named_property("clone", clone)?;
+        let is_big_number: JsFunction =
+            env.create_function_from_closure("isBigNumber", |ctx: CallContext<'_>| {
+                let value: Option<JsUnknown> = optional_arg(&ctx, 0)?;
+                Ok(value.is_some_and(|v| BigNumber::is_big_number(*ctx.env, v)))
+            })?;
+        constructor.set_named_property("isBigNumber", is_big_number)?;
+        Ok(constructor)
+    }
+
     #[napi]
     pub fn absolute_value(&self) -> Self {
-        BigNumber(self.0.abs())
+        self.if_non_finite(f64::abs)
+            .unwrap_or_else(|| self.1.in_range(self.0.abs()))
     }
 
     #[napi]
     pub fn abs(&self) -> Self {
-        BigNumber(self.0.abs())
+        self.absolute_value()
     }
 
+    /// 1, -1 or 0 as `this` is above, below or equal to `n`, or `null` if
+    /// either is `NaN`.
     #[napi]
-    pub fn compared_to(&self, n: &BigNumber) -> i32 {
-        match self.0.cmp(&n.0) {
+    pub fn compared_to(&self, n: &BigNumber) -> Option<i32> {
+        let ordering = match (self.2, n.2) {
+            (None, None) => self.0.cmp(&n.0),
+            _ => self.approx_f64().partial_cmp(&n.approx_f64())?,
+        };
+        Some(match ordering {
             std::cmp::Ordering::Less => -1,
             std::cmp::Ordering::Equal => 0,
             std::cmp::Ordering::Greater => 1,
-        }
+        })
     }
 
+    /// The number of decimal places, `null` if `this` is not finite.
     #[napi]
-    pub fn decimal_places(&self) -> i64 {
-        self.0.fractional_digit_count()
+    pub fn decimal_places(&self) -> Option<i64> {
+        self.2.is_none().then(|| self.0.fractional_digit_count())
     }
 
     #[napi]
-    pub fn dp(&self) -> i64 {
-        self.0.fractional_digit_count()
+    pub fn dp(&self) -> Option<i64> {
+        self.decimal_places()
     }
 
+    /// `this / n`, rounded to `DECIMAL_PLACES` with `rm`.
     #[napi]
-    pub fn divided_by(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 / &n.0)
+    pub fn divided_by(&self, n: &BigNumber, rm: Option<RoundingMode>) -> Result<Self> {
+        if n.is_zero() {
+            return Err(Error::new(Status::InvalidArg, "Division by zero"));
+        }
+        if let Some(result) = self.if_either_non_finite(n, |a, b| a / b) {
+            return Ok(result);
+        }
+        let config = self.1.get();
+        let rm = rm.unwrap_or(config.rounding_mode);
+        let quotient = divide(&self.0, &n.0, config.decimal_places, rm)?;
+        Ok(self.1.in_range(quotient))
     }
 
     #[napi]
-    pub fn div(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 / &n.0)
+    pub fn div(&self, n: &BigNumber, rm: Option<RoundingMode>) -> Result<Self> {
+        self.divided_by(n, rm)
     }
 
+    /// `this / n` rounded to an integer with `rm`, `ROUND_DOWN` by default for
+    /// the integer part.
     #[napi]
-    pub fn divided_to_integer_by(&self, n: &BigNumber) -> Self {
-        let v = &self.0 / &n.0;
-        BigNumber(v.round(0))
+    pub fn divided_to_integer_by(&self, n: &BigNumber, rm: Option<RoundingMode>) -> Result<Self> {
+        if n.is_zero() {
+            return Err(Error::new(Status::InvalidArg, "Division by zero"));
+        }
+        if let Some(result) = self.if_either_non_finite(n, |a, b| (a / b).trunc()) {
+            return Ok(result);
+        }
+        let rm = rm.unwrap_or(RoundingMode::RoundDown);
+        Ok(self.1.in_range(divide(&self.0, &n.0, 0, rm)?))
     }
 
     #[napi]
-    pub fn idiv(&self, n: &BigNumber) -> Self {
-        self.divided_to_integer_by(n)
+    pub fn idiv(&self, n: &BigNumber, rm: Option<RoundingMode>) -> Result<Self> {
+        self.divided_to_integer_by(n, rm)
     }
 
     #[napi]
     pub fn negated(&self) -> Self {
-        BigNumber(-&self.0)
+        self.if_non_finite(|v| -v)
+            .unwrap_or_else(|| self.1.in_range(-&self.0))
     }
 
     #[napi]
     pub fn plus(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 + &n.0)
+        self.if_either_non_finite(n, |a, b| a + b)
+            .unwrap_or_else(|| self.1.in_range(&self.0 + &n.0))
     }
 
     #[napi]
     pub fn minus(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 - &n.0)
+        self.if_either_non_finite(n, |a, b| a - b)
+            .unwrap_or_else(|| self.1.in_range(&self.0 - &n.0))
     }
 
     #[napi]
     pub fn times(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 * &n.0)
+        self.if_either_non_finite(n, |a, b| a * b)
+            .unwrap_or_else(|| self.1.in_range(&self.0 * &n.0))
     }
 
+    /// `e^this`, rounded to `DECIMAL_PLACES` with `ROUNDING_MODE`.
     #[napi]
     pub fn exp(&self) -> Self {
-        BigNumber(self.0.exp())
+        if let Some(result) = self.if_non_finite(f64::exp) {
+            return result;
+        }
+        // `e^this` has an exponent of about `this / ln(10)`, enough to tell a
+        // result far beyond `RANGE`.
+        let e = (self.0.to_f64().unwrap_or(0.0) / std::f64::consts::LN_10).floor() as i128;
+        if let Some(result) = self
+            .1
+            .beyond_range(e.saturating_sub(1), e.saturating_add(1), false)
+        {
+            return result;
+        }
+        let config = self.1.get();
+        let digits = (config.decimal_places + e as i64 + 5).max(5) as u64;
+        let power = exp(&self.0, digits);
+        self.1.in_range(round_to_places(
+            &power,
+            config.decimal_places,
+            config.rounding_mode,
+        ))
     }
 
     #[napi]
-    pub fn integer_value(&self) -> Self {
-        BigNumber(self.0.round(0))
+    pub fn integer_value(&self, rm: Option<RoundingMode>) -> Self {
+        if let Some(result) = self.if_non_finite(f64::trunc) {
+            return result;
+        }
+        let rm = rm.unwrap_or(self.1.get().rounding_mode);
+        self.1.in_range(rm.round(&self.0, 0))
     }
 
+    /// `this - n q`, with `q` the quotient `dividedToIntegerBy` gives with `rm`.
+    /// `ROUND_DOWN` by default, which gives the remainder of `%`.
     #[napi]
-    pub fn modulo(&self, n: &BigNumber) -> Self {
-        BigNumber(&self.0 % &n.0)
+    pub fn modulo(&self, n: &BigNumber, rm: Option<RoundingMode>) -> Result<Self> {
+        if n.is_zero() {
+            return Err(Error::new(Status::InvalidArg, "Division by zero"));
+        }
+        if self.2.is_none() && n.2.is_some_and(f64::is_infinite) {
+            // As in JS, a finite number is its own remainder by `Infinity`.
+            return Ok(self.1.in_range(self.0.clone()));
+        }
+        if let Some(result) = self.if_either_non_finite(n, |a, b| a % b) {
+            return Ok(result);
+        }
+        let quotient = self.divided_to_integer_by(n, rm)?;
+        Ok(self.1.in_range(&self.0 - &n.0 * quotient.0))
     }
 
     #[napi]
     pub fn square(&self) -> BigNumber {
-        BigNumber(self.0.square())
+        self.if_non_finite(|v| v * v)
+            .unwrap_or_else(|| self.1.in_range(self.0.square()))
     }
 
     #[napi]
     pub fn cube(&self) -> BigNumber {
-        BigNumber(self.0.cube())
+        self.if_non_finite(|v| v * v * v)
+            .unwrap_or_else(|| self.1.in_range(self.0.cube()))
     }
 
+    /// The square root, rounded to `DECIMAL_PLACES` with `ROUNDING_MODE`, or
+    /// `null` for a negative number.
     #[napi]
     pub fn sqrt(&self) -> Option<BigNumber> {
-        self.0.sqrt().map(BigNumber)
+        match self.2 {
+            Some(v) if v < 0.0 => return None,
+            // `Infinity` and `NaN` are their own square roots.
+            Some(v) => return Some(self.1.from_f64(v)),
+            None => {}
+        }
+        let config = self.1.get();
+        // Enough significant digits for `DECIMAL_PLACES` after the point.
+        let digits = (config.decimal_places + self.exponent() / 2 + 5).max(5) as u64;
+        let root = self.0.sqrt_with_context(&context(digits))?;
+        let root = round_to_places(&root, config.decimal_places, config.rounding_mode);
+        Some(self.1.in_range(root))
     }
 
+    /// The cube root, rounded to `DECIMAL_PLACES` with `ROUNDING_MODE`.
     #[napi]
     pub fn cbrt(&self) -> BigNumber {
-        BigNumber(self.0.cbrt())
+        if let Some(result) = self.if_non_finite(f64::cbrt) {
+            return result;
+        }
+        let config = self.1.get();
+        // Enough significant digits for `DECIMAL_PLACES` after the point.
+        let digits = (config.decimal_places + self.exponent() / 3 + 5).max(5) as u64;
+        let root = self.0.cbrt_with_context(&context(digits));
+        let root = round_to_places(&root, config.decimal_places, config.rounding_mode);
+        self.1.in_range(root)
     }
 
+    /// `this` to the power `n`. With `m`, `n` must be a non-negative integer
+    /// and the result is `this^n % m`, computed without the full power. A
+    /// negative or fractional `n` gives a result rounded to `DECIMAL_PLACES`.
     #[napi]
-    pub fn to_fixed(&self, n: Option<u32>) -> String {
-        match n {
-            Some(n) => self.0.with_prec(n as u64).to_string(),
+    pub fn exponentiated_by(&self, n: &BigNumber, m: Option<&BigNumber>) -> Result<Self> {
+        if let Some(m) = m {
+            return self.modular_power(n, m);
+        }
+        if let Some(result) = self.if_either_non_finite(n, f64::powf) {
+            return Ok(result);
+        }
+        if n.0.is_integer() {
+            return self.integer_power(n);
+        }
+        if self.0.is_negative() {
+            return Err(Error::new(
+                Status::InvalidArg,
+                "A negative number has no real fractional power",
+            ));
+        }
+        if self.0.is_zero() {
+            return match n.0.is_positive() {
+                true => Ok(self.1.in_range(BigDecimal::zero())),
+                false => Err(Error::new(Status::InvalidArg, "Division by zero")),
+            };
+        }
+        // The estimate is enough to tell a result far beyond `RANGE`, which
+        // would take too long to compute.
+        let e = (n.0.to_f64().unwrap_or(f64::INFINITY) * log10(&self.0)).floor() as i128;
+        if let Some(result) = self
+            .1
+            .beyond_range(e.saturating_sub(1), e.saturating_add(1), false)
+        {
+            return Ok(result);
+        }
+        let config = self.1.get();
+        Ok(self.1.in_range(fractional_power(
+            &self.0,
+            &n.0,
+            config.decimal_places,
+            config.rounding_mode,
+        )))
+    }
+
+    #[napi]
+    pub fn pow(&self, n: &BigNumber, m: Option<&BigNumber>) -> Result<Self> {
+        self.exponentiated_by(n, m)
+    }
+
+    /// The number in plain notation with `dp` decimal places, rounded with `rm`.
+    #[napi]
+    pub fn to_fixed(&self, dp: Option<u32>, rm: Option<RoundingMode>) -> String {
+        if self.2.is_some() {
+            return self.to_string();
+        }
+        match dp {
+            Some(dp) => rm
+                .unwrap_or(self.1.get().rounding_mode)
+                .round(&self.0, dp as i64)
+                .to_plain_string(),
             None => self.0.to_plain_string(),
         }
     }
 
+    /// The number in scientific notation with `n` significant digits, rounded
+    /// with `rm`.
     #[napi]
-    pub fn to_exponential(&self, n: u32) -> String {
-        self.0.with_prec(n as u64).to_scientific_notation()
+    pub fn to_exponential(&self, n: u32, rm: Option<RoundingMode>) -> String {
+        if self.2.is_some() {
+            return self.to_string();
+        }
+        let dp = n.max(1) as i64 - 1 - self.exponent();
+        let rounded = rm.unwrap_or(self.1.get().rounding_mode).round(&self.0, dp);
+        // Rounding up can add a digit, e.g. 9.99 to 10.0.
+        let dp = n.max(1) as i64 - 1 - exponent(&rounded);
+        rounded
+            .with_scale_round(dp, bigdecimal::RoundingMode::Down)
+            .to_scientific_notation()
     }
 
+    /// In scientific notation if the exponent is at or beyond `EXPONENTIAL_AT`,
+    /// otherwise plain.
     #[napi(js_name = "toString")]
     pub fn to_string_js(&self) -> String {
-        self.to_string()
+        let (neg, pos) = self.1.get().exponential_at;
+        let e = self.exponent();
+        if self.2.is_some() || e <= neg || e >= pos {
+            self.to_string()
+        } else {
+            self.0.to_plain_string()
+        }
     }
 
+    /// The number as a raw JSON number, or `null` for `NaN` and `Infinity` as
+    /// with a JS number.
     #[napi(js_name = "toJSON")]
     pub fn to_json(&self, env: Env) -> Result<JsUnknown> {
         let json: JsObject = env.get_global()?.get_named_property_unchecked("JSON")?;
         let raw_json: Function<'_, String, JsUnknown> =
             json.get_named_property_unchecked("rawJSON")?;
 
-        let str = self.0.to_scientific_notation();
+        let str = match self.2 {
+            Some(_) => "null".to_string(),
+            None => self.0.to_scientific_notation(),
+        };
         raw_json.call(str)
     }
 }
 
+impl BigNumber {
+    /// The BigNumber the constructor with `config` makes from `n` and `base`.
+    fn parse(
+        n: Either3<JsNumber, String, &BigNumber>,
+        base: Option<u32>,
+        config: &SharedConfig,
+    ) -> Result<Self> {
+        match n {
+            Either3::A(n) => Ok(config.from_f64(n.get_double()?)),
+            Either3::B(s) => match s.as_str() {
+                "NaN" => Ok(config.from_f64(f64::NAN)),
+                "Infinity" | "+Infinity" => Ok(config.from_f64(f64::INFINITY)),
+                "-Infinity" => Ok(config.from_f64(f64::NEG_INFINITY)),
+                s => {
+                    let n = BigDecimal::from_str_radix(s, base.unwrap_or(10))
+                        .map_err(|e| Error::new(Status::InvalidArg, e))?;
+                    Ok(config.in_range(n))
+                }
+            },
+            Either3::C(n) => Ok(match n.2 {
+                Some(v) => config.from_f64(v),
+                None => config.in_range(n.0.clone()),
+            }),
+        }
+    }
+
+    /// The exponent of the number in scientific notation, e.g. 2 for `123` and
+    /// -3 for `0.00123`.
+    pub(crate) fn exponent(&self) -> i64 {
+        exponent(&self.0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.2.is_none() && self.0.is_zero()
+    }
+
+    /// The number as an `f64`, kept finite and non-zero if it is, which is all
+    /// that the result of arithmetic with `Infinity` or `NaN` depends on.
+    fn approx_f64(&self) -> f64 {
+        if let Some(v) = self.2 {
+            return v;
+        }
+        let v = self.0.to_f64().unwrap_or(0.0);
+        if v.is_infinite() {
+            f64::MAX.copysign(v)
+        } else if v == 0.0 && !self.0.is_zero() {
+            f64::MIN_POSITIVE.copysign(if self.0.is_negative() { -1.0 } else { 1.0 })
+        } else {
+            v
+        }
+    }
+
+    /// What `op` gives for `this` as a JS number, if it is not finite.
+    fn if_non_finite(&self, op: impl FnOnce(f64) -> f64) -> Option<Self> {
+        self.2.map(|v| self.1.from_f64(op(v)))
+    }
+
+    /// What `op` gives for `this` and `n` as JS numbers, if either is not
+    /// finite.
+    fn if_either_non_finite(
+        &self,
+        n: &BigNumber,
+        op: impl FnOnce(f64, f64) -> f64,
+    ) -> Option<Self> {
+        if self.2.is_none() && n.2.is_none() {
+            return None;
+        }
+        Some(self.1.from_f64(op(self.approx_f64(), n.approx_f64())))
+    }
+
+    /// `this^n` for an integer `n`, exact unless `n` is negative.
+    fn integer_power(&self, n: &BigNumber) -> Result<Self> {
+        let too_large = || Error::new(Status::InvalidArg, "Exponent too large");
+        let e = n.0.to_i64().ok_or_else(too_large)?;
+        if !self.0.is_zero() {
+            // With `10^k <= |this| < 10^(k + 1)`, the power has an exponent
+            // from `k e` to `(k + 1) e`, so one beyond `RANGE` is known before
+            // the power is computed.
+            let k = self.exponent() as i128;
+            let (a, b) = (k * e as i128, (k + 1) * e as i128);
+            let negative = self.0.is_negative() && e % 2 != 0;
+            if let Some(result) = self.1.beyond_range(a.min(b), a.max(b), negative) {
+                return Ok(result);
+            }
+        }
+        let e_abs = u32::try_from(e.unsigned_abs()).map_err(|_| too_large())?;
+        let (digits, scale) = self.0.as_bigint_and_scale();
+        let scale = scale.checked_mul(e_abs as i64).ok_or_else(too_large)?;
+        let power = BigDecimal::new(digits.pow(e_abs), scale);
+        if e >= 0 {
+            return Ok(self.1.in_range(power));
+        }
+        if power.is_zero() {
+            return Err(Error::new(Status::InvalidArg, "Division by zero"));
+        }
+        // Enough significant digits for `DECIMAL_PLACES` after the point.
+        let config = self.1.get();
+        let digits = (config.decimal_places - exponent(&power) + 5).max(5) as u64;
+        let inverse = power.inverse_with_context(&context(digits));
+        let inverse = round_to_places(&inverse, config.decimal_places, config.rounding_mode);
+        Ok(self.1.in_range(inverse))
+    }
+
+    /// `this^n % m`, by square-and-multiply so the power is never held.
+    fn modular_power(&self, n: &BigNumber, m: &BigNumber) -> Result<Self> {
+        if n.2.is_some() || !n.0.is_integer() || n.0.is_negative() {
+            return Err(Error::new(
+                Status::InvalidArg,
+                "The exponent must be a non-negative integer with a modulus",
+            ));
+        }
+        if m.is_zero() {
+            return Err(Error::new(Status::InvalidArg, "Division by zero"));
+        }
+        if self.2.is_some() || m.2.is_some() {
+            // Nothing to gain without finite numbers, and `modulo` gives the
+            // results of JS for them.
+            return self.exponentiated_by(n, None)?.modulo(m, None);
+        }
+        let (e, _) = n.0.with_scale(0).into_bigint_and_exponent();
+        let base = &self.0 % &m.0;
+        let mut result = BigDecimal::one() % &m.0;
+        for i in (0..e.bits()).rev() {
+            result = result.square() % &m.0;
+            if e.bit(i) {
+                result = result * &base % &m.0;
+            }
+        }
+        Ok(self.1.in_range(result))
+    }
+}
+
+/// Argument `index` of a call, `None` if it is missing, `undefined` or `null`.
+fn optional_arg<T: FromNapiValue>(ctx: &CallContext<'_>, index: usize) -> Result<Option<T>> {
+    if index < ctx.length {
+        ctx.get::<Option<T>>(index)
+    } else {
+        Ok(None)
+    }
+}
+
+/// The negative and positive exponent of the `name` setting, given as a
+/// magnitude or as `[negative, positive]`.
+fn exponent_range(name: &str, range: Either<u32, Vec<i64>>) -> Result<(i64, i64)> {
+    let max = MAX_DECIMAL_PLACES as i64;
+    let (neg, pos) = match range {
+        Either::A(n) => (-(n as i64), n as i64),
+        Either::B(v) if v.len() == 2 => (v[0], v[1]),
+        Either::B(_) => {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("{name} must be a number or an array of two numbers"),
+            ));
+        }
+    };
+    if !(-max..=0).contains(&neg) || !(0..=max).contains(&pos) {
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("{name} must be from -{max} to 0 and from 0 to {max}"),
+        ));
+    }
+    Ok((neg, pos))
+}
+
+/// The exponent of `x` in scientific notation, 0 for zero.
+fn exponent(x: &BigDecimal) -> i64 {
+    if x.is_zero() {
+        return 0;
+    }
+    let (_, scale) = x.as_bigint_and_scale();
+    x.digits() as i64 - 1 - scale
+}
+
+/// `x` rounded to `dp` decimal places with `rm`, without trailing zeros after
+/// the point.
+fn round_to_places(x: &BigDecimal, dp: i64, rm: RoundingMode) -> BigDecimal {
+    let rounded = rm.round(x, dp).normalized();
+    match rounded.fractional_digit_count() < 0 {
+        true => rounded.with_scale(0),
+        false => rounded,
+    }
+}
+
+/// `a / b` for a non-zero `b`, rounded to `dp` decimal places with `rm`.
+fn divide(a: &BigDecimal, b: &BigDecimal, dp: i64, rm: RoundingMode) -> Result<BigDecimal> {
+    // The quotient cut off after `dp + 1` places, followed by a 1 if anything
+    // was cut off, rounds the same as the exact quotient.
+    let (a_digits, a_scale) = a.as_bigint_and_scale();
+    let (b_digits, b_scale) = b.as_bigint_and_scale();
+    let shift = dp + 1 + b_scale - a_scale;
+    let ten = BigInt::from(10);
+    let (num, den) = match u32::try_from(shift.unsigned_abs()) {
+        Ok(s) if shift >= 0 => (a_digits.as_ref() * ten.pow(s), b_digits.into_owned()),
+        Ok(s) => (a_digits.into_owned(), b_digits.as_ref() * ten.pow(s)),
+        // `a` is too small next to `b` for the quotient to show in `dp + 1`
+        // places, and only its sign matters.
+        Err(_) if shift < 0 => (a_digits.signum(), b_digits.signum() * 2),
+        Err(_) => return Err(Error::new(Status::InvalidArg, "Exponent too large")),
+    };
+    let quotient = &num / &den;
+    let remainder = &num % &den;
+    let sticky = if remainder.is_zero() {
+        0
+    } else if remainder.sign() == den.sign() {
+        1
+    } else {
+        -1
+    };
+    let truncated = BigDecimal::new(quotient * 10 + sticky, dp + 2);
+    Ok(round_to_places(&truncated, dp, rm))
+}
+
+/// A context that keeps `digits` significant digits.
+fn context(digits: u64) -> Context {
+    Context::default().with_precision(NonZeroU64::new(digits.max(1)).unwrap())
+}
+
+/// `10^-digits`.
+fn epsilon(digits: u64) -> BigDecimal {
+    BigDecimal::new(BigInt::one(), digits as i64)
+}
+
+/// `x^n` for a positive `x` and a fractional `n`, as `e^(n ln x)`, rounded to
+/// `dp` decimal places with `rm`.
+fn fractional_power(x: &BigDecimal, n: &BigDecimal, dp: i64, rm: RoundingMode) -> BigDecimal {
+    // The result has about `n log10(x)` digits before the point, which all
+    // need to be right, and the error of `ln x` is multiplied by `n`.
+    let int_digits = (n.to_f64().unwrap_or(0.0) * log10(x)).max(0.0) as u64 + 1;
+    let digits = dp.max(0) as u64 + int_digits + 5;
+    let n_digits = (exponent(n) + 1).max(0) as u64;
+    let y = n * ln(x, digits + n_digits + 5);
+    round_to_places(&exp(&y, digits), dp, rm)
+}
+
+/// An estimate of `log10(x)` for a positive `x`, beyond the range of `f64` too.
+fn log10(x: &BigDecimal) -> f64 {
+    x.to_f64()
+        .map(f64::log10)
+        .filter(|v| v.is_finite())
+        .unwrap_or(exponent(x) as f64)
+}
+
+/// The natural logarithm of a positive `x`, to `digits` significant digits.
+fn ln(x: &BigDecimal, digits: u64) -> BigDecimal {
+    // ln(x) = 2^k ln(x^(1/2^k)), with k square roots bringing x close enough
+    // to 1 for the series to converge fast. Each one costs some precision.
+    let one = BigDecimal::one();
+    let bound = BigDecimal::new(BigInt::one(), 3);
+    let mut root = x.clone();
+    let mut k = 0;
+    let ctx = loop {
+        let ctx = context(digits + 10 + k / 3);
+        if (&root - &one).abs() <= bound {
+            break ctx;
+        }
+        root = root.sqrt_with_context(&ctx).unwrap();
+        k += 1;
+    };
+    // ln(a) = 2 atanh(z) = 2 (z + z^3/3 + z^5/5 + ...), with z = (a - 1) / (a + 1).
+    let z = ctx.multiply(&(&root - &one), &(&root + &one).inverse_with_context(&ctx));
+    let z2 = ctx.multiply(&z, &z);
+    let eps = epsilon(digits + 10 + k / 3);
+    let mut power = z.clone();
+    let mut sum = z;
+    for n in (3u64..).step_by(2) {
+        power = ctx.multiply(&power, &z2);
+        let term = ctx.round_decimal(&power / BigDecimal::from(n));
+        if term.abs() < eps {
+            break;
+        }
+        sum += term;
+    }
+    context(digits).round_decimal(sum * BigDecimal::from(BigInt::one() << (k + 1)))
+}
+
+/// `e^x` to `digits` significant digits.
+fn exp(x: &BigDecimal, digits: u64) -> BigDecimal {
+    // e^x = (e^(x/2^k))^(2^k), with x/2^k small enough for the series to
+    // converge fast. Each squaring costs some precision.
+    let bound = BigDecimal::new(BigInt::one(), 2);
+    let mut r = x.clone();
+    let mut k = 0;
+    while r.abs() > bound {
+        r = r.half();
+        k += 1;
+    }
+    let ctx = context(digits + 10 + k / 3);
+    let eps = epsilon(digits + 10 + k / 3);
+    let mut term = BigDecimal::one();
+    let mut sum = BigDecimal::one();
+    for n in 1u64.. {
+        term = ctx.round_decimal(ctx.multiply(&term, &r) / BigDecimal::from(n));
+        if term.abs() < eps {
+            break;
+        }
+        sum += &term;
+    }
+    for _ in 0..k {
+        sum = ctx.multiply(&sum, &sum);
+    }
+    context(digits).round_decimal(sum)
+}
+
+/// A BigNumber of the `BigNumber` class, not of a clone.
+impl From<BigDecimal> for BigNumber {
+    fn from(value: BigDecimal) -> Self {
+        SharedConfig::default().in_range(value)
+    }
+}
+
 impl Display for BigNumber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.write_scientific_notation(f)
+        match self.2 {
+            // `NaN`, `Infinity` or `-Infinity`, as in JS.
+            Some(v) => f.write_str(ryu_js::Buffer::new().format(v)),
+            None => self.0.write_scientific_notation(f),
+        }
     }
 }
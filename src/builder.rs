@@ -0,0 +1,665 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use bigdecimal::BigDecimal;
+use bigdecimal::num_bigint::{BigInt, Sign};
+use napi_ohos::bindgen_prelude::{BigInt64Array, Float64Array, FnArgs, Function, ToNapiValue};
+use napi_ohos::{
+    Env, Error, JsFunction, JsObject, JsString, JsUnknown, NapiValue, Property, Ref, Result,
+    Status, ValueType,
+};
+use widestring::{U16Str, U16String, u16str};
+
+use crate::bignumber::BigNumber;
+use crate::error::ParseResult;
+use crate::lossless::LosslessNumber;
+use crate::options::Options;
+use crate::path::{PathSegment, from_pointer, to_pointer};
+use crate::sendable;
+
+/// `(this: holder, key: string, value: unknown) => unknown`, as in `JSON.parse`.
+pub type Reviver<'a> = Function<'a, FnArgs<(JsString, JsUnknown)>, JsUnknown>;
+
+type NumberHandler<'a> = Function<'a, FnArgs<(JsString, JsObject)>, JsUnknown>;
+
+pub const PROTO_KEY: &U16Str = u16str!("__proto__");
+pub const CONSTRUCTOR_KEY: &U16Str = u16str!("constructor");
+pub const PROTOTYPE_KEY: &U16Str = u16str!("prototype");
+
+/// A number after the parser has applied the number options.
+#[derive(Debug, Clone)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+    BigInt(BigInt),
+    BigNumber(BigDecimal),
+    /// The text of the number, for `lossless_numbers`.
+    Lossless(String),
+}
+
+/// The elements of an array of numbers, for `typed_arrays`.
+#[derive(Debug, Clone)]
+pub enum NumberArray {
+    Float64(Vec<f64>),
+    /// Integers, some of which are outside the safe range.
+    BigInt64(Vec<i64>),
+}
+
+/// Receives the values recognized by `JsonParser`.
+///
+/// Children are always built before the container they are added to.
+pub trait Builder {
+    type Value;
+    type Array;
+    type Object;
+
+    fn null(&mut self) -> ParseResult<Self::Value>;
+    fn boolean(&mut self, value: bool) -> ParseResult<Self::Value>;
+    fn number(&mut self, value: Number) -> ParseResult<Self::Value>;
+    /// Whether `raw_number` should be called.
+    fn wants_raw_numbers(&self) -> bool {
+        false
+    }
+    /// Gets the first say on a number, given its text and where it is in the
+    /// document. Returning `None` applies the number options as usual.
+    fn raw_number(
+        &mut self,
+        _raw: &str,
+        _path: &[PathSegment],
+    ) -> ParseResult<Option<Self::Value>> {
+        Ok(None)
+    }
+    /// Called with a number that lost precision, for `precision_loss: "warn"`.
+    fn precision_loss(&mut self, _raw: &str, _path: &[PathSegment]) -> ParseResult<()> {
+        Ok(())
+    }
+    fn string(&mut self, value: U16String) -> ParseResult<Self::Value>;
+    /// A string that `parse_dates` selected.
+    fn date(&mut self, value: U16String) -> ParseResult<Self::Value>;
+    /// A string that is known to be ASCII, still as UTF-8 bytes.
+    fn ascii_string(&mut self, value: &[u8]) -> ParseResult<Self::Value> {
+        // SAFETY: ASCII is valid UTF-8.
+        let s = unsafe { std::str::from_utf8_unchecked(value) };
+        self.string(U16String::from_str(s))
+    }
+
+    /// Whether `number_array` can create something better than an ordinary array.
+    fn wants_number_arrays(&self) -> bool {
+        false
+    }
+    /// An array of numbers, for `typed_arrays`.
+    fn number_array(&mut self, values: NumberArray) -> ParseResult<Self::Value> {
+        let mut array = self.begin_array()?;
+        match values {
+            NumberArray::Float64(values) => {
+                for v in values {
+                    let value = self.number(Number::Float(v))?;
+                    self.push(&mut array, value)?;
+                }
+            }
+            NumberArray::BigInt64(values) => {
+                for v in values {
+                    let value = self.number(Number::BigInt(BigInt::from(v)))?;
+                    self.push(&mut array, value)?;
+                }
+            }
+        }
+        self.end_array(array)
+    }
+
+    fn begin_array(&mut self) -> ParseResult<Self::Array>;
+    fn push(&mut self, array: &mut Self::Array, value: Self::Value) -> ParseResult<()>;
+    fn end_array(&mut self, array: Self::Array) -> ParseResult<Self::Value>;
+
+    fn begin_object(&mut self) -> ParseResult<Self::Object>;
+    /// Called with each kept key as soon as it is parsed, before its value.
+    fn key(&mut self, _key: &U16Str) -> ParseResult<()> {
+        Ok(())
+    }
+    fn insert(
+        &mut self,
+        object: &mut Self::Object,
+        key: U16String,
+        value: Self::Value,
+    ) -> ParseResult<()>;
+    fn end_object(&mut self, object: Self::Object) -> ParseResult<Self::Value>;
+
+    /// Called once with the root value after the whole input has been consumed.
+    fn finish(&mut self, value: Self::Value) -> ParseResult<Self::Value> {
+        Ok(value)
+    }
+}
+
+/// Keys longer than this, in UTF-16 code units, are not cached.
+const MAX_CACHED_KEY_LEN: usize = 64;
+/// Keys after this many distinct ones are not cached.
+const MAX_CACHED_KEYS: usize = 4096;
+
+/// JS strings for keys that outlive a parse, so that a `JsonParser` used for
+/// many documents of the same shape creates each key once.
+#[derive(Default)]
+pub struct KeyRefs(HashMap<U16String, Ref<()>>);
+
+impl KeyRefs {
+    /// Releases the strings.
+    pub fn clear(&mut self, env: Env) -> Result<()> {
+        for (_, mut key) in self.0.drain() {
+            key.unref(env)?;
+        }
+        Ok(())
+    }
+}
+
+pub enum JsArray {
+    Array {
+        obj: JsObject,
+        len: u32,
+    },
+    /// The elements of a Sendable array, which is created once they are all known.
+    Sendable(Vec<JsUnknown>),
+}
+
+pub enum JsObj {
+    Object(JsObject),
+    /// The properties of a Sendable object, which is created once they are all known.
+    Sendable(Vec<(U16String, JsUnknown)>),
+}
+
+/// `Map` and the methods of `Map.prototype`, for `objects_as_maps`.
+struct MapFns {
+    ctor: JsFunction,
+    set: JsFunction,
+    get: JsFunction,
+    delete: JsFunction,
+    keys: JsFunction,
+    /// `Array.from`, for a snapshot of the keys.
+    array_from: JsFunction,
+}
+
+/// Creates JS values directly.
+pub struct JsBuilder<'a> {
+    env: Env,
+    reviver: Option<Reviver<'a>>,
+    number_handler: Option<NumberHandler<'a>>,
+    map: Option<MapFns>,
+    /// `Date`, for `parse_dates`.
+    date: Option<JsFunction>,
+    freeze: bool,
+    sendable: bool,
+    resolve_refs: bool,
+    /// JS strings already created for keys, or `None` if keys are not cached.
+    keys: Option<HashMap<U16String, JsString>>,
+    /// Where the keys are also kept for later parses.
+    key_refs: Option<&'a mut KeyRefs>,
+}
+
+impl<'a> JsBuilder<'a> {
+    pub fn new(env: &'a Env, opts: &Options, reviver: Option<Reviver<'a>>) -> Result<Self> {
+        let number_handler = match &opts.number_handler {
+            Some(handler) => Some(handler.borrow_back(env)?),
+            None => None,
+        };
+        let map = if opts.objects_as_maps.is_some_and(|e| e) {
+            let ctor: JsFunction = env.get_global()?.get_named_property("Map")?;
+            let prototype: JsObject = ctor.coerce_to_object()?.get_named_property("prototype")?;
+            let array: JsObject = env.get_global()?.get_named_property("Array")?;
+            Some(MapFns {
+                ctor,
+                set: prototype.get_named_property("set")?,
+                get: prototype.get_named_property("get")?,
+                delete: prototype.get_named_property("delete")?,
+                keys: prototype.get_named_property("keys")?,
+                array_from: array.get_named_property("from")?,
+            })
+        } else {
+            None
+        };
+        let date = if opts.parse_dates.is_some_and(|e| e) {
+            Some(env.get_global()?.get_named_property("Date")?)
+        } else {
+            None
+        };
+        let freeze = opts.freeze.is_some_and(|e| e);
+        let sendable = opts.sendable.is_some_and(|e| e);
+        if sendable && (reviver.is_some() || map.is_some() || date.is_some() || freeze) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                "sendable cannot be used with a reviver, objectsAsMaps, freeze or parseDates",
+            ));
+        }
+        let resolve_refs = opts.resolve_refs.is_some_and(|e| e);
+        if resolve_refs && (map.is_some() || freeze || sendable) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                "resolveRefs cannot be used with objectsAsMaps, freeze or sendable",
+            ));
+        }
+        Ok(JsBuilder {
+            env: *env,
+            reviver,
+            number_handler,
+            map,
+            date,
+            freeze,
+            sendable,
+            resolve_refs,
+            keys: Some(HashMap::new()),
+            key_refs: None,
+        })
+    }
+
+    /// Turns off the key cache, for when the handles of earlier values are
+    /// released during the parse.
+    pub fn without_key_cache(mut self) -> Self {
+        self.keys = None;
+        self
+    }
+
+    /// Keeps the key strings in `key_refs` too, and reuses the ones it has.
+    pub fn with_key_refs(mut self, key_refs: &'a mut KeyRefs) -> Self {
+        self.key_refs = Some(key_refs);
+        self
+    }
+
+    /// Creates the JS string for a key, reusing the one from an earlier object
+    /// or an earlier parse if it is short.
+    fn key_string(&mut self, key: U16String) -> Result<JsString> {
+        let Some(keys) = &mut self.keys else {
+            return self.env.create_string_utf16(key.as_slice());
+        };
+        if let Some(s) = keys.get(&key) {
+            return Ok(*s);
+        }
+        let cached = key.len() <= MAX_CACHED_KEY_LEN;
+
+        let s = match self.key_refs.as_deref_mut() {
+            Some(KeyRefs(refs)) => match refs.get(&key) {
+                Some(r) => self.env.get_reference_value(r)?,
+                None => {
+                    let s = self.env.create_string_utf16(key.as_slice())?;
+                    if cached && refs.len() < MAX_CACHED_KEYS {
+                        refs.insert(key.clone(), self.env.create_reference(s)?);
+                    }
+                    s
+                }
+            },
+            None => self.env.create_string_utf16(key.as_slice())?,
+        };
+        if cached && keys.len() < MAX_CACHED_KEYS {
+            keys.insert(key, s);
+        }
+        Ok(s)
+    }
+
+    /// Creates a Sendable object. A repeated key keeps its first position and
+    /// its last value, as in `JSON.parse`.
+    fn end_sendable_object(
+        &mut self,
+        properties: Vec<(U16String, JsUnknown)>,
+    ) -> ParseResult<JsUnknown> {
+        let mut positions = HashMap::with_capacity(properties.len());
+        let mut unique: Vec<(JsString, JsUnknown)> = Vec::with_capacity(properties.len());
+        for (key, value) in properties {
+            match positions.entry(key) {
+                Entry::Occupied(e) => unique[*e.get()].1 = value,
+                Entry::Vacant(e) => {
+                    let name = self.key_string(e.key().clone())?;
+                    e.insert(unique.len());
+                    unique.push((name, value));
+                }
+            }
+        }
+        Ok(sendable::create_object(&self.env, unique)?)
+    }
+
+    /// Calls the reviver, if any, on a value whose children have already been revived.
+    fn revive<T: ToNapiValue>(
+        &self,
+        holder: T,
+        key: JsString,
+        value: JsUnknown,
+    ) -> Result<JsUnknown> {
+        match &self.reviver {
+            Some(reviver) => reviver.apply(holder, (key, value).into()),
+            None => Ok(value),
+        }
+    }
+
+    /// Calls the reviver on every element of a complete array, in order, like
+    /// `JSON.parse`. An element it returns `undefined` for is deleted.
+    fn revive_elements(&self, mut array: JsObject, len: u32) -> Result<()> {
+        for i in 0..len {
+            let key = self.env.create_string(&i.to_string())?;
+            let value = self.revive(array, key, array.get_element(i)?)?;
+            if value.get_type()? == ValueType::Undefined {
+                array.delete_element(i)?;
+            } else {
+                array.set_element(i, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the reviver on every property of a complete object or `Map`, in
+    /// key order, like `JSON.parse`. A property it returns `undefined` for is
+    /// deleted.
+    fn revive_properties(&self, mut object: JsObject) -> Result<()> {
+        let keys = match &self.map {
+            Some(map) => {
+                let keys = map.keys.call_without_args(Some(&object))?;
+                map.array_from.call(None, &[keys])?.coerce_to_object()?
+            }
+            None => object.get_property_names()?,
+        };
+        for i in 0..keys.get_array_length_unchecked()? {
+            let key: JsString = keys.get_element_unchecked(i)?;
+            let value: JsUnknown = match &self.map {
+                Some(map) => map.get.call(Some(&object), &[key])?,
+                None => object.get_property(key)?,
+            };
+            let value = self.revive(object, key, value)?;
+            let deleted = value.get_type()? == ValueType::Undefined;
+            match &self.map {
+                Some(map) if deleted => {
+                    map.delete.call(Some(&object), &[key])?;
+                }
+                Some(map) => {
+                    map.set.call(Some(&object), &[key.into_unknown(), value])?;
+                }
+                None if deleted => {
+                    object.delete_property(key)?;
+                }
+                None => object.set_property(key, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces every `{"$ref": pointer}` object below `root` with the value
+    /// `pointer` leads to, for `resolve_refs`.
+    fn resolve_refs(&self, root: JsUnknown) -> Result<()> {
+        // Find all the references first, so that the walk only sees a tree.
+        let mut refs = Vec::new();
+        let mut stack = Vec::new();
+        if root.get_type()? == ValueType::Object {
+            stack.push(unsafe { root.cast::<JsObject>() });
+        }
+        while let Some(obj) = stack.pop() {
+            if obj.is_typedarray()? {
+                continue;
+            }
+            let keys = obj.get_property_names()?;
+            for i in 0..keys.get_array_length_unchecked()? {
+                let key: JsString = keys.get_element_unchecked(i)?;
+                let value: JsUnknown = obj.get_property(key)?;
+                if value.get_type()? != ValueType::Object {
+                    continue;
+                }
+                let value: JsObject = unsafe { value.cast() };
+                match ref_pointer(value)? {
+                    Some(pointer) => refs.push((obj, key, pointer)),
+                    None => stack.push(value),
+                }
+            }
+        }
+
+        for (mut holder, key, pointer) in refs {
+            if let Some(target) = self.lookup(root, &pointer)? {
+                holder.set_property(key, target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The value at the JSON pointer `pointer` below `root`, if there is one.
+    fn lookup(&self, root: JsUnknown, pointer: &str) -> Result<Option<JsUnknown>> {
+        let Some(tokens) = from_pointer(pointer) else {
+            return Ok(None);
+        };
+        let mut value = root;
+        for token in tokens {
+            if value.get_type()? != ValueType::Object {
+                return Ok(None);
+            }
+            let obj: JsObject = unsafe { value.cast() };
+            let key = self.env.create_string(&token)?;
+            if !obj.has_own_property_js(key)? {
+                return Ok(None);
+            }
+            value = obj.get_property(key)?;
+        }
+        Ok(Some(value))
+    }
+}
+
+/// The pointer of an object that is exactly `{"$ref": pointer}`.
+fn ref_pointer(obj: JsObject) -> Result<Option<String>> {
+    if obj.is_array()? || !obj.has_own_property("$ref")? {
+        return Ok(None);
+    }
+    if obj.get_property_names()?.get_array_length_unchecked()? != 1 {
+        return Ok(None);
+    }
+    let pointer: JsUnknown = obj.get_named_property("$ref")?;
+    if pointer.get_type()? != ValueType::String {
+        return Ok(None);
+    }
+    let pointer: JsString = unsafe { pointer.cast() };
+    Ok(Some(pointer.into_utf8()?.into_owned()?))
+}
+
+impl Builder for JsBuilder<'_> {
+    type Value = JsUnknown;
+    type Array = JsArray;
+    type Object = JsObj;
+
+    fn null(&mut self) -> ParseResult<JsUnknown> {
+        let null = self.env.get_null()?;
+        Ok(null.into_unknown())
+    }
+
+    fn boolean(&mut self, value: bool) -> ParseResult<JsUnknown> {
+        let b = self.env.get_boolean(value)?;
+        Ok(b.into_unknown())
+    }
+
+    fn number(&mut self, value: Number) -> ParseResult<JsUnknown> {
+        match value {
+            Number::Int(v) => Ok(self.env.create_int64(v)?.into_unknown()),
+            Number::Float(v) => Ok(self.env.create_double(v)?.into_unknown()),
+            Number::BigInt(v) => {
+                let (sign, int) = v.into_parts();
+                let sign_bit = sign == Sign::Minus;
+                let words = int.to_u64_digits();
+                let bigint = self.env.create_bigint_from_words(sign_bit, words)?;
+                Ok(bigint.into_unknown()?)
+            }
+            Number::BigNumber(_) | Number::Lossless(_) if self.sendable => Err(Error::new(
+                Status::InvalidArg,
+                "A BigNumber or LosslessNumber is not Sendable, use useNativeBigInt or storeAsString",
+            )
+            .into()),
+            Number::BigNumber(v) => {
+                let n = BigNumber::from(v);
+                let napi_value = unsafe { BigNumber::to_napi_value(self.env.raw(), n)? };
+                Ok(unsafe { JsUnknown::from_raw(self.env.raw(), napi_value)? })
+            }
+            Number::Lossless(v) => {
+                let n = LosslessNumber(v);
+                let napi_value = unsafe { LosslessNumber::to_napi_value(self.env.raw(), n)? };
+                Ok(unsafe { JsUnknown::from_raw(self.env.raw(), napi_value)? })
+            }
+        }
+    }
+
+    fn wants_raw_numbers(&self) -> bool {
+        self.number_handler.is_some()
+    }
+
+    fn raw_number(&mut self, raw: &str, path: &[PathSegment]) -> ParseResult<Option<JsUnknown>> {
+        let Some(handler) = &self.number_handler else {
+            return Ok(None);
+        };
+        let raw = self.env.create_string(raw)?;
+        let mut keys = self.env.create_empty_array()?;
+        for (i, segment) in path.iter().enumerate() {
+            match segment {
+                PathSegment::Index(index) => keys.set_element(i as u32, *index)?,
+                PathSegment::Key(key) => {
+                    let key = self.env.create_string_utf16(key.as_slice())?;
+                    keys.set_element(i as u32, key)?
+                }
+            }
+        }
+
+        let value = handler.call((raw, keys).into())?;
+        if value.get_type()? == ValueType::Undefined {
+            return Ok(None);
+        }
+        Ok(Some(value))
+    }
+
+    fn precision_loss(&mut self, raw: &str, path: &[PathSegment]) -> ParseResult<()> {
+        let console: JsObject = self.env.get_global()?.get_named_property("console")?;
+        let warn: Function<'_, JsString, JsUnknown> = console.get_named_property("warn")?;
+        let message = format!("Precision lost parsing {raw} at {:?}", to_pointer(path));
+        warn.apply(console, self.env.create_string(&message)?)?;
+        Ok(())
+    }
+
+    fn string(&mut self, value: U16String) -> ParseResult<JsUnknown> {
+        let s = self.env.create_string_utf16(value.as_slice())?;
+        Ok(s.into_unknown())
+    }
+
+    fn date(&mut self, value: U16String) -> ParseResult<JsUnknown> {
+        let s = self.env.create_string_utf16(value.as_slice())?;
+        match &self.date {
+            Some(ctor) => Ok(ctor.new_instance(&[s])?.into_unknown()),
+            None => Ok(s.into_unknown()),
+        }
+    }
+
+    fn ascii_string(&mut self, value: &[u8]) -> ParseResult<JsUnknown> {
+        // ASCII is a subset of Latin-1, which needs no transcoding.
+        let s = self.env.create_string_latin1(value)?;
+        Ok(s.into_unknown())
+    }
+
+    fn wants_number_arrays(&self) -> bool {
+        // Elements of a typed array cannot be revived.
+        self.reviver.is_none()
+    }
+
+    fn number_array(&mut self, values: NumberArray) -> ParseResult<JsUnknown> {
+        let napi_value = match values {
+            NumberArray::Float64(values) => unsafe {
+                Float64Array::to_napi_value(self.env.raw(), Float64Array::new(values))?
+            },
+            NumberArray::BigInt64(values) => unsafe {
+                BigInt64Array::to_napi_value(self.env.raw(), BigInt64Array::new(values))?
+            },
+        };
+        Ok(unsafe { JsUnknown::from_raw(self.env.raw(), napi_value)? })
+    }
+
+    fn begin_array(&mut self) -> ParseResult<JsArray> {
+        if self.sendable {
+            return Ok(JsArray::Sendable(Vec::new()));
+        }
+        let obj = self.env.create_empty_array()?;
+        Ok(JsArray::Array { obj, len: 0 })
+    }
+
+    fn push(&mut self, array: &mut JsArray, value: JsUnknown) -> ParseResult<()> {
+        let (obj, len) = match array {
+            JsArray::Array { obj, len } => (obj, len),
+            JsArray::Sendable(elements) => {
+                elements.push(value);
+                return Ok(());
+            }
+        };
+        obj.set_element(*len, value)?;
+        *len += 1;
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: JsArray) -> ParseResult<JsUnknown> {
+        match array {
+            JsArray::Array { mut obj, len } => {
+                if self.reviver.is_some() {
+                    self.revive_elements(obj, len)?;
+                }
+                if self.freeze {
+                    obj.freeze()?;
+                }
+                Ok(obj.into_unknown())
+            }
+            JsArray::Sendable(elements) => Ok(sendable::create_array(&self.env, elements)?),
+        }
+    }
+
+    fn begin_object(&mut self) -> ParseResult<JsObj> {
+        if self.sendable {
+            return Ok(JsObj::Sendable(Vec::new()));
+        }
+        match &self.map {
+            Some(map) => Ok(JsObj::Object(map.ctor.new_instance::<JsUnknown>(&[])?)),
+            None => Ok(JsObj::Object(self.env.create_object()?)),
+        }
+    }
+
+    fn insert(&mut self, object: &mut JsObj, key: U16String, value: JsUnknown) -> ParseResult<()> {
+        let object = match object {
+            JsObj::Object(object) => object,
+            JsObj::Sendable(properties) => {
+                properties.push((key, value));
+                return Ok(());
+            }
+        };
+        let is_proto = key.as_ustr() == PROTO_KEY;
+        let key = self.key_string(key)?;
+        if let Some(map) = &self.map {
+            map.set.call(Some(&*object), &[key.into_unknown(), value])?;
+        } else if is_proto {
+            // Assigning `__proto__` would call the `Object.prototype` setter and
+            // replace the prototype, so define it as an own property instead.
+            let property = Property::new()
+                .with_utf8_name("__proto__")?
+                .with_value(&value);
+            object.define_properties(&[property])?;
+        } else {
+            object.set_property(key, value)?;
+        }
+        Ok(())
+    }
+
+    fn end_object(&mut self, object: JsObj) -> ParseResult<JsUnknown> {
+        let mut object = match object {
+            JsObj::Object(object) => object,
+            JsObj::Sendable(properties) => return self.end_sendable_object(properties),
+        };
+        if self.reviver.is_some() {
+            self.revive_properties(object)?;
+        }
+        if self.freeze {
+            object.freeze()?;
+        }
+        Ok(object.into_unknown())
+    }
+
+    fn finish(&mut self, value: JsUnknown) -> ParseResult<JsUnknown> {
+        let value = if self.reviver.is_some() {
+            let mut holder = self.env.create_object()?;
+            holder.set_named_property("", value)?;
+            let key = self.env.create_string("")?;
+            self.revive(holder, key, value)?
+        } else {
+            value
+        };
+        if self.resolve_refs {
+            self.resolve_refs(value)?;
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,76 @@
+use napi_derive_ohos::napi;
+
+/// A naming convention for object keys. Only ASCII letters change case; other
+/// characters are kept as they are.
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    /// E.g. `userId` and `HTTPServer` become `user_id` and `http_server`.
+    Snake,
+    /// E.g. `user_id` becomes `userId`. Leading and repeated underscores are kept.
+    Camel,
+}
+
+const UNDERSCORE: u16 = b'_' as u16;
+
+fn is_upper(unit: u16) -> bool {
+    u8::try_from(unit).is_ok_and(|c| c.is_ascii_uppercase())
+}
+
+fn is_lower_or_digit(unit: u16) -> bool {
+    u8::try_from(unit).is_ok_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+fn to_lower(unit: u16) -> u16 {
+    if is_upper(unit) { unit + 32 } else { unit }
+}
+
+fn to_upper(unit: u16) -> u16 {
+    match u8::try_from(unit) {
+        Ok(c) if c.is_ascii_lowercase() => unit - 32,
+        _ => unit,
+    }
+}
+
+impl KeyCase {
+    /// `key`, a string of UTF-16 code units, in this case.
+    pub fn convert(self, key: &[u16]) -> Vec<u16> {
+        match self {
+            KeyCase::Snake => to_snake(key),
+            KeyCase::Camel => to_camel(key),
+        }
+    }
+}
+
+fn to_snake(key: &[u16]) -> Vec<u16> {
+    let mut snake = Vec::with_capacity(key.len() + 4);
+    for (i, &unit) in key.iter().enumerate() {
+        if is_upper(unit) && i > 0 {
+            let prev = key[i - 1];
+            let next = key.get(i + 1).copied().unwrap_or(0);
+            // A word starts after a lowercase letter or digit, or at the last
+            // capital of an acronym, as in `HTTPServer`.
+            if is_lower_or_digit(prev) || (is_upper(prev) && is_lower_or_digit(next)) {
+                snake.push(UNDERSCORE);
+            }
+        }
+        snake.push(to_lower(unit));
+    }
+    snake
+}
+
+fn to_camel(key: &[u16]) -> Vec<u16> {
+    let mut camel = Vec::with_capacity(key.len());
+    let mut upper_next = false;
+    for (i, &unit) in key.iter().enumerate() {
+        let after_word = i > 0 && key[i - 1] != UNDERSCORE;
+        let next = key.get(i + 1).copied().unwrap_or(UNDERSCORE);
+        if unit == UNDERSCORE && after_word && next != UNDERSCORE {
+            upper_next = true;
+            continue;
+        }
+        camel.push(if upper_next { to_upper(unit) } else { unit });
+        upper_next = false;
+    }
+    camel
+}
@@ -0,0 +1,62 @@
+use std::io::Read;
+
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{ArrayBuffer, Either, Uint8Array};
+use napi_ohos::{Env, Error, JsUnknown, Result, Status};
+
+use crate::builder::{JsBuilder, Reviver};
+use crate::error::{JsonErrorCode, ParseError, PositionedError, with_code};
+use crate::options::Options;
+use crate::parse::JsonParser;
+
+#[napi(string_enum = "lowercase")]
+pub enum Encoding {
+    Gzip,
+    /// zlib-wrapped deflate, as in `Content-Encoding: deflate`.
+    Deflate,
+}
+
+#[napi(object)]
+pub struct Compression {
+    pub encoding: Encoding,
+}
+
+/// Decompresses `buf` and parses the result. `max_input_bytes` limits the
+/// decompressed size.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_compressed(
+    env: Env,
+    buf: Either<ArrayBuffer, Uint8Array>,
+    compression: Compression,
+    options: Option<Options>,
+    reviver: Option<Reviver<'_>>,
+) -> Result<JsUnknown> {
+    let bytes: &[u8] = match &buf {
+        Either::A(b) => b,
+        Either::B(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let decoder: Box<dyn Read> = match compression.encoding {
+        Encoding::Gzip => Box::new(MultiGzDecoder::new(bytes)),
+        Encoding::Deflate => Box::new(ZlibDecoder::new(bytes)),
+    };
+
+    // Stop reading one byte past the limit, so a small bomb cannot fill memory.
+    let limit = opts.max_input_bytes.map_or(u64::MAX, |max| max as u64 + 1);
+    let mut input = Vec::new();
+    decoder.take(limit).read_to_end(&mut input).map_err(|e| {
+        let error = Error::new(Status::InvalidArg, format!("Failed to decompress: {e}"));
+        with_code(&env, error, JsonErrorCode::DecompressFailed)
+    })?;
+    if input.len() as u64 == limit {
+        let error = PositionedError::new(ParseError::InputTooLarge, &input, input.len());
+        return Err(error.into_error(&env));
+    }
+
+    let builder = JsBuilder::new(&env, &opts, reviver)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+    let mut parser = JsonParser::new(&input, &opts, builder);
+    parser.parse().map_err(|e| e.into_error(&env))
+}
@@ -0,0 +1,202 @@
+use napi_derive_ohos::napi;
+use napi_ohos::{Env, JsObject, Result};
+
+use crate::options::Options;
+use crate::parse::JsonParser;
+use crate::validate::NullBuilder;
+
+/// Parses `s` into a concrete syntax tree that keeps every character of the
+/// input, including whitespace and comments, so that editors and tools can
+/// inspect and rewrite a document without losing its formatting.
+///
+/// Joining the `text` of the tokens in document order gives back `s`.
+#[napi(ts_return_type = "CstNode")]
+#[allow(dead_code)]
+pub fn parse_cst(env: Env, s: String, options: Option<Options>) -> Result<JsObject> {
+    let opts = options.unwrap_or_default();
+    // Report syntax errors as `parse` does, so the tree is only built for valid input.
+    let mut parser = JsonParser::new(s.as_bytes(), &opts, NullBuilder);
+    parser.parse().map_err(|e| e.into_error(&env))?;
+    CstBuilder::new(&env, &s).build()
+}
+
+/// A document, array, object or member whose children are still being added.
+struct Node {
+    kind: &'static str,
+    obj: JsObject,
+    children: JsObject,
+    len: u32,
+}
+
+impl Node {
+    fn push(&mut self, child: JsObject) -> Result<()> {
+        self.children.set_element(self.len, child)?;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+/// Splits valid input into tokens and nests them on an explicit stack.
+struct CstBuilder<'a> {
+    env: &'a Env,
+    input: &'a str,
+    pos: usize,
+    line: u32,
+    column: u32,
+}
+
+impl<'a> CstBuilder<'a> {
+    fn new(env: &'a Env, input: &'a str) -> Self {
+        CstBuilder {
+            env,
+            input,
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn build(mut self) -> Result<JsObject> {
+        let mut stack = vec![self.node("document")?];
+        while let Some((kind, text)) = self.next_token() {
+            let token = self.token(kind, text)?;
+            match (kind, text) {
+                ("punctuation", "{" | "[") => {
+                    let mut node = self.node(if text == "{" { "object" } else { "array" })?;
+                    node.push(token)?;
+                    stack.push(node);
+                }
+                ("punctuation", "}" | "]") => {
+                    close_member(&mut stack)?;
+                    let mut node = stack.pop().unwrap();
+                    node.push(token)?;
+                    stack.last_mut().unwrap().push(node.obj)?;
+                }
+                ("punctuation", ",") => {
+                    close_member(&mut stack)?;
+                    stack.last_mut().unwrap().push(token)?;
+                }
+                ("string" | "identifier" | "number" | "literal", _)
+                    if stack.last().unwrap().kind == "object" =>
+                {
+                    let mut member = self.node("member")?;
+                    member.push(token)?;
+                    stack.push(member);
+                }
+                _ => stack.last_mut().unwrap().push(token)?,
+            }
+            self.advance(text);
+        }
+        close_member(&mut stack)?;
+        Ok(stack.swap_remove(0).obj)
+    }
+
+    /// The kind and text of the token at the current position.
+    fn next_token(&self) -> Option<(&'static str, &'a str)> {
+        let rest = &self.input[self.pos..];
+        let c = rest.chars().next()?;
+        let (kind, len) = match c {
+            '{' | '}' | '[' | ']' | ':' | ',' => ("punctuation", 1),
+            '"' | '\'' => ("string", string_len(rest)),
+            '/' if rest[1..].starts_with('/') => {
+                ("comment", rest.find(['\n', '\r']).unwrap_or(rest.len()))
+            }
+            '/' if rest[1..].starts_with('*') => (
+                "comment",
+                rest[2..].find("*/").map_or(rest.len(), |i| i + 4),
+            ),
+            _ if is_space(c) => (
+                "whitespace",
+                rest.find(|c| !is_space(c)).unwrap_or(rest.len()),
+            ),
+            _ => {
+                let len = rest
+                    .find(|c: char| is_space(c) || "{}[]:,\"'/".contains(c))
+                    .unwrap_or(rest.len())
+                    .max(c.len_utf8());
+                (word_kind(&rest[..len]), len)
+            }
+        };
+        Some((kind, &rest[..len]))
+    }
+
+    /// Moves past `text`, keeping the line and column up to date.
+    fn advance(&mut self, text: &str) {
+        self.pos += text.len();
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
+    /// An object with the kind and position of the token or node at the current position.
+    fn located(&self, kind: &str) -> Result<JsObject> {
+        let mut obj = self.env.create_object()?;
+        obj.set_named_property("kind", kind)?;
+        obj.set_named_property("offset", self.pos as u32)?;
+        obj.set_named_property("line", self.line)?;
+        obj.set_named_property("column", self.column)?;
+        Ok(obj)
+    }
+
+    fn token(&self, kind: &str, text: &str) -> Result<JsObject> {
+        let mut obj = self.located(kind)?;
+        obj.set_named_property("text", text)?;
+        Ok(obj)
+    }
+
+    fn node(&self, kind: &'static str) -> Result<Node> {
+        let mut obj = self.located(kind)?;
+        let children = self.env.create_empty_array()?;
+        obj.set_named_property("children", children)?;
+        Ok(Node {
+            kind,
+            obj,
+            children,
+            len: 0,
+        })
+    }
+}
+
+/// Adds a finished member to its object. A member ends at the `,` or `}` after
+/// its value, so it also holds the whitespace and comments before that.
+fn close_member(stack: &mut Vec<Node>) -> Result<()> {
+    if stack.last().is_some_and(|node| node.kind == "member") {
+        let member = stack.pop().unwrap();
+        stack.last_mut().unwrap().push(member.obj)?;
+    }
+    Ok(())
+}
+
+fn is_space(c: char) -> bool {
+    c.is_whitespace() || c == '\u{FEFF}'
+}
+
+/// The length of the string literal at the start of `s`, with its quotes.
+fn string_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let quote = bytes[0];
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// The kind of a token that is not punctuation, a string or a comment.
+fn word_kind(word: &str) -> &'static str {
+    match word {
+        "true" | "false" | "null" => "literal",
+        "NaN" | "Infinity" => "number",
+        _ if word.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) => "number",
+        _ => "identifier",
+    }
+}
@@ -1,6 +1,49 @@
 use std::fmt;
+use std::ptr;
 
-use napi_ohos::{Error, Status};
+use napi_derive_ohos::napi;
+use napi_ohos::{
+    Env, Error, JsObject, JsUnknown, NapiRaw, NapiValue, Result, Status, ValueType, check_status,
+    sys,
+};
+
+pub type ParseResult<T> = std::result::Result<T, ParseError>;
+
+/// The `code` property of errors thrown by `parse` and `stringify`.
+#[napi(string_enum = "UPPER_SNAKE")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonErrorCode {
+    UnexpectedCharacter,
+    UnexpectedEnd,
+    InvalidNumber,
+    InvalidEscape,
+    ExpectedColon,
+    ExpectedCommaOrEnd,
+    TrailingChars,
+    InvalidUtf8,
+    DepthLimit,
+    NodeLimit,
+    InputTooLarge,
+    StringTooLong,
+    ForbiddenKey,
+    Cancelled,
+    PrecisionLoss,
+    NumberOverflow,
+    /// `parseCompressed` got input that is not valid gzip or deflate data.
+    DecompressFailed,
+    /// `stringify` found an object that contains itself.
+    Circular,
+    /// `stringify` was told to fail on a kind of value it found.
+    UnsupportedValue,
+    /// `stringify` read a property whose getter threw.
+    GetterThrew,
+    /// The output of `stringify` grew past `max_output_bytes`.
+    OutputTooLarge,
+    /// A `StreamingParser` was used after `end()`.
+    InvalidState,
+    /// A napi call failed.
+    Internal,
+}
 
 #[derive(Debug)]
 pub enum ParseError {
@@ -11,6 +54,15 @@ pub enum ParseError {
     ExpectedColon,
     ExpectedCommaOrEnd,
     TrailingCharacters,
+    InvalidUtf8,
+    DepthLimitExceeded,
+    NodeLimitExceeded,
+    InputTooLarge,
+    StringTooLong,
+    ForbiddenKey,
+    Cancelled,
+    PrecisionLoss,
+    NumberOverflow,
     NapiError(Error),
 }
 
@@ -30,11 +82,44 @@ impl AsRef<str> for ParseError {
             ParseError::ExpectedColon => "ExpectedColon",
             ParseError::ExpectedCommaOrEnd => "ExpectedCommaOrEnd",
             ParseError::TrailingCharacters => "TrailingCharacters",
+            ParseError::InvalidUtf8 => "InvalidUtf8",
+            ParseError::DepthLimitExceeded => "DepthLimitExceeded",
+            ParseError::NodeLimitExceeded => "NodeLimitExceeded",
+            ParseError::InputTooLarge => "InputTooLarge",
+            ParseError::StringTooLong => "StringTooLong",
+            ParseError::ForbiddenKey => "ForbiddenKey",
+            ParseError::Cancelled => "Cancelled",
+            ParseError::PrecisionLoss => "PrecisionLoss",
+            ParseError::NumberOverflow => "NumberOverflow",
             ParseError::NapiError(error) => error.status.as_ref(),
         }
     }
 }
 
+impl ParseError {
+    pub fn code(&self) -> JsonErrorCode {
+        match self {
+            ParseError::UnexpectedCharacter(_) => JsonErrorCode::UnexpectedCharacter,
+            ParseError::UnexpectedEndOfInput => JsonErrorCode::UnexpectedEnd,
+            ParseError::InvalidNumber => JsonErrorCode::InvalidNumber,
+            ParseError::InvalidEscapeSequence(_) => JsonErrorCode::InvalidEscape,
+            ParseError::ExpectedColon => JsonErrorCode::ExpectedColon,
+            ParseError::ExpectedCommaOrEnd => JsonErrorCode::ExpectedCommaOrEnd,
+            ParseError::TrailingCharacters => JsonErrorCode::TrailingChars,
+            ParseError::InvalidUtf8 => JsonErrorCode::InvalidUtf8,
+            ParseError::DepthLimitExceeded => JsonErrorCode::DepthLimit,
+            ParseError::NodeLimitExceeded => JsonErrorCode::NodeLimit,
+            ParseError::InputTooLarge => JsonErrorCode::InputTooLarge,
+            ParseError::StringTooLong => JsonErrorCode::StringTooLong,
+            ParseError::ForbiddenKey => JsonErrorCode::ForbiddenKey,
+            ParseError::Cancelled => JsonErrorCode::Cancelled,
+            ParseError::PrecisionLoss => JsonErrorCode::PrecisionLoss,
+            ParseError::NumberOverflow => JsonErrorCode::NumberOverflow,
+            ParseError::NapiError(_) => JsonErrorCode::Internal,
+        }
+    }
+}
+
 impl From<Error> for ParseError {
     fn from(err: Error) -> Self {
         ParseError::NapiError(err)
@@ -49,3 +134,261 @@ impl From<ParseError> for Error {
         }
     }
 }
+
+/// Where a parse error happened. `offset` is in bytes of the UTF-8 input,
+/// `line` and `column` start at 1 and the column is counted in characters.
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(input: &[u8], offset: usize) -> Self {
+        let offset = offset.min(input.len());
+        let before = &input[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&c| c == b'\n')
+            .map_or(0, |i| i + 1);
+        let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+        // Count UTF-8 lead bytes, i.e. characters.
+        let column = before[line_start..]
+            .iter()
+            .filter(|&&c| (c & 0xC0) != 0x80)
+            .count()
+            + 1;
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+}
+
+/// Characters of context shown on each side of the failure point.
+const SNIPPET_CONTEXT: usize = 24;
+
+/// A `ParseError` with the position in the input where it happened.
+#[derive(Debug)]
+pub struct PositionedError {
+    pub error: ParseError,
+    pub position: Position,
+    /// The line around the failure point, followed by a line with a caret under it.
+    pub snippet: String,
+    /// The index of the failing record, when parsing several documents.
+    pub record: Option<usize>,
+}
+
+impl fmt::Display for PositionedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.error, self.position.line, self.position.column
+        )?;
+        if let Some(record) = self.record {
+            write!(f, " in record {record}")?;
+        }
+        if !self.snippet.is_empty() {
+            write!(f, "\n{}", self.snippet)?;
+        }
+        Ok(())
+    }
+}
+
+/// Renders the line containing `offset`, cut to `SNIPPET_CONTEXT` characters on
+/// each side, with a caret marker below the character at `offset`.
+fn snippet(input: &[u8], offset: usize) -> String {
+    let line_start = input[..offset]
+        .iter()
+        .rposition(|&c| c == b'\n')
+        .map_or(0, |i| i + 1);
+    let line_end = input[offset..]
+        .iter()
+        .position(|&c| c == b'\n')
+        .map_or(input.len(), |i| offset + i);
+    let before = String::from_utf8_lossy(&input[line_start..offset]);
+    let after = String::from_utf8_lossy(&input[offset..line_end]);
+    // Tabs and other control characters would shift the caret.
+    let clean = |c: char| if c.is_control() { ' ' } else { c };
+
+    let before: Vec<char> = before.chars().map(clean).collect();
+    let skipped = before.len().saturating_sub(SNIPPET_CONTEXT);
+    let mut line = String::new();
+    if skipped > 0 {
+        line.push_str("...");
+    }
+    line.extend(&before[skipped..]);
+    let caret = line.chars().count();
+
+    let mut after = after.chars().map(clean);
+    line.extend(after.by_ref().take(SNIPPET_CONTEXT));
+    if after.next().is_some() {
+        line.push_str("...");
+    }
+    if line.trim().is_empty() {
+        return String::new();
+    }
+
+    format!("    {line}\n    {}^", " ".repeat(caret))
+}
+
+impl PositionedError {
+    pub fn new(error: ParseError, input: &[u8], offset: usize) -> Self {
+        let position = Position::new(input, offset);
+        PositionedError {
+            error,
+            position,
+            snippet: snippet(input, position.offset),
+            record: None,
+        }
+    }
+
+    /// Creates the JS error with `code`, `offset`, `line` and `column` properties,
+    /// and `record` if it is set.
+    pub fn into_error(self, env: &Env) -> Error {
+        let message = self.to_string();
+        let position = self.position;
+        let record = self.record;
+        let error = match self.error {
+            ParseError::NapiError(e) => return with_code(env, e, JsonErrorCode::Internal),
+            e => create_error(env, message, e.code()),
+        };
+
+        let error = error.and_then(|mut obj| {
+            obj.set_named_property("offset", position.offset as u32)?;
+            obj.set_named_property("line", position.line as u32)?;
+            obj.set_named_property("column", position.column as u32)?;
+            if let Some(record) = record {
+                obj.set_named_property("record", record as u32)?;
+            }
+            Ok(obj)
+        });
+        match error {
+            Ok(obj) => Error::from(obj.into_unknown()),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Why `stringify` failed.
+#[derive(Debug)]
+pub enum StringifyError {
+    CircularStructure,
+    /// An array or object nested deeper than the `max_depth` option.
+    DepthLimitExceeded(u32),
+    /// A value the options say not to convert, e.g. `"a TypedArray"`.
+    UnsupportedValue(&'static str),
+    /// A getter threw, with what it threw as a string.
+    GetterThrew(String),
+    /// The output grew past the `max_output_bytes` option.
+    OutputTooLarge(usize),
+    NapiError(Error),
+}
+
+pub type StringifyResult<T> = std::result::Result<T, StringifyError>;
+
+impl fmt::Display for StringifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringifyError::CircularStructure => write!(f, "Converting circular structure to JSON"),
+            StringifyError::DepthLimitExceeded(max) => {
+                write!(f, "Nesting deeper than the maximum depth of {max}")
+            }
+            StringifyError::UnsupportedValue(what) => write!(f, "Cannot convert {what} to JSON"),
+            StringifyError::GetterThrew(thrown) => write!(f, "Getter threw {thrown}"),
+            StringifyError::OutputTooLarge(max) => {
+                write!(f, "Output longer than the maximum of {max} bytes")
+            }
+            StringifyError::NapiError(error) => write!(f, "{}", error.reason),
+        }
+    }
+}
+
+impl From<Error> for StringifyError {
+    fn from(err: Error) -> Self {
+        StringifyError::NapiError(err)
+    }
+}
+
+impl StringifyError {
+    pub fn code(&self) -> JsonErrorCode {
+        match self {
+            StringifyError::CircularStructure => JsonErrorCode::Circular,
+            StringifyError::DepthLimitExceeded(_) => JsonErrorCode::DepthLimit,
+            StringifyError::UnsupportedValue(_) => JsonErrorCode::UnsupportedValue,
+            StringifyError::GetterThrew(_) => JsonErrorCode::GetterThrew,
+            StringifyError::OutputTooLarge(_) => JsonErrorCode::OutputTooLarge,
+            StringifyError::NapiError(_) => JsonErrorCode::Internal,
+        }
+    }
+
+    /// Creates the JS error with `code` and `path` properties. `path` is where
+    /// in the value the error happened, e.g. `items[3].price`.
+    ///
+    /// An exception thrown by a JS callback, e.g. `toJSON` or the replacer, is
+    /// passed through with a `path` property added, if it is an object.
+    pub fn into_error(self, env: &Env, path: &str) -> Error {
+        if let StringifyError::NapiError(e) = &self
+            && e.status == Status::PendingException
+        {
+            return match take_exception(env).and_then(|thrown| with_path(thrown, path)) {
+                Ok(thrown) => Error::from(thrown),
+                Err(e) => e,
+            };
+        }
+        let message = if path.is_empty() {
+            self.to_string()
+        } else {
+            format!("{self} at {path}")
+        };
+        let error = create_error(env, message, self.code()).and_then(|mut obj| {
+            obj.set_named_property("path", path)?;
+            Ok(obj)
+        });
+        match error {
+            Ok(obj) => Error::from(obj.into_unknown()),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Clears the pending JS exception and returns it.
+pub fn take_exception(env: &Env) -> Result<JsUnknown> {
+    let mut exception = ptr::null_mut();
+    check_status!(unsafe { sys::napi_get_and_clear_last_exception(env.raw(), &mut exception) })?;
+    unsafe { JsUnknown::from_raw(env.raw(), exception) }
+}
+
+/// Sets the `path` property of `thrown`, if it is an object that doesn't have one.
+fn with_path(thrown: JsUnknown, path: &str) -> Result<JsUnknown> {
+    if path.is_empty() || thrown.get_type()? != ValueType::Object {
+        return Ok(thrown);
+    }
+    let mut obj = thrown.coerce_to_object()?;
+    if !obj.has_named_property("path")? {
+        obj.set_named_property("path", path)?;
+    }
+    Ok(obj.into_unknown())
+}
+
+fn create_error(env: &Env, message: String, code: JsonErrorCode) -> Result<JsObject> {
+    let mut obj = env.create_error(Error::new(Status::GenericFailure, message))?;
+    obj.set_named_property("code", code)?;
+    Ok(obj)
+}
+
+/// Adds a `code` property to an error raised by napi.
+///
+/// Exceptions thrown by JS callbacks, e.g. the reviver, are passed through unchanged.
+pub fn with_code(env: &Env, error: Error, code: JsonErrorCode) -> Error {
+    if error.status == Status::PendingException {
+        return error;
+    }
+    match create_error(env, error.reason.clone(), code) {
+        Ok(obj) => Error::from(obj.into_unknown()),
+        Err(e) => e,
+    }
+}
@@ -1,8 +1,21 @@
 mod bignumber;
+mod builder;
+mod case;
+mod compressed;
+mod cst;
 mod error;
+mod lossless;
 mod options;
 mod parse;
+mod parser;
+mod path;
+mod sendable;
+mod stream;
 mod stringify;
+mod tokenize;
+mod validate;
+mod value;
+mod writer;
 
 use mimalloc::MiMalloc;
 
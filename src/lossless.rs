@@ -0,0 +1,66 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{Function, JavaScriptClassExt};
+use napi_ohos::{Env, Error, JsObject, JsUnknown, Result, Status};
+
+use crate::bignumber::BigNumber;
+use crate::parse::invalid_number_at;
+
+/// A number kept as the exact text it had in the document, e.g. `1.000` or
+/// `1e2`, so that it is written back unchanged.
+#[napi]
+#[derive(Debug, Clone)]
+pub struct LosslessNumber(pub(crate) String);
+
+#[napi]
+impl LosslessNumber {
+    #[napi(constructor)]
+    pub fn new(value: String) -> Result<Self> {
+        if invalid_number_at(value.as_bytes(), false).is_some() {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("{value:?} is not a JSON number"),
+            ));
+        }
+        Ok(LosslessNumber(value))
+    }
+
+    #[napi]
+    pub fn is_lossless_number(env: Env, value: JsUnknown) -> bool {
+        LosslessNumber::instance_of(env, &value).is_ok_and(|v| v)
+    }
+
+    /// The text of the number.
+    #[napi(getter)]
+    pub fn value(&self) -> String {
+        self.0.clone()
+    }
+
+    /// The nearest `number`, which may lose precision.
+    #[napi(js_name = "valueOf")]
+    pub fn value_of(&self) -> f64 {
+        // The text was validated, so only the range can be a problem.
+        self.0.parse().unwrap_or(f64::NAN)
+    }
+
+    #[napi]
+    pub fn to_big_number(&self) -> Result<BigNumber> {
+        let n = BigDecimal::from_str(&self.0).map_err(|e| Error::new(Status::InvalidArg, e))?;
+        Ok(BigNumber::from(n))
+    }
+
+    #[napi(js_name = "toString")]
+    pub fn to_string_js(&self) -> String {
+        self.0.clone()
+    }
+
+    #[napi(js_name = "toJSON")]
+    pub fn to_json(&self, env: Env) -> Result<JsUnknown> {
+        let json: JsObject = env.get_global()?.get_named_property_unchecked("JSON")?;
+        let raw_json: Function<'_, String, JsUnknown> =
+            json.get_named_property_unchecked("rawJSON")?;
+        raw_json.call(self.0.clone())
+    }
+}
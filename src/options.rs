@@ -1,9 +1,194 @@
+use std::collections::HashMap;
+
 use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{FnArgs, FunctionRef, Unknown};
+use napi_ohos::threadsafe_function::ThreadsafeFunction;
+use napi_ohos::{JsObject, JsString, JsUnknown, Status};
+
+use crate::case::KeyCase;
+
+/// `(raw: string, path: (string | number)[]) => unknown`
+pub type NumberHandler = FunctionRef<FnArgs<(JsString, JsObject)>, JsUnknown>;
+
+/// `(consumed: number, total: number) => void`, in bytes.
+pub type ProgressCallback =
+    ThreadsafeFunction<FnArgs<(f64, f64)>, Unknown<'static>, FnArgs<(f64, f64)>, Status, false>;
 
 #[napi(object)]
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct Options {
     pub always_parse_as_big: Option<bool>,
+    /// Return integers outside the safe range as `BigInt` rather than `BigNumber`.
     pub use_native_big_int: Option<bool>,
+    /// Return integers outside the safe range as decimal strings rather than
+    /// `BigInt` or `BigNumber`, e.g. for code that passes them to `JSON.stringify`.
+    pub big_int_as_string: Option<bool>,
     pub parse_float_as_big: Option<bool>,
+    /// Return non-integer numbers as their original text. Takes precedence over
+    /// `parse_float_as_big`.
+    pub parse_float_as_string: Option<bool>,
+    /// Return numbers that would become a `BigInt` or `BigNumber`, or lose precision
+    /// as a `number`, as their original text.
+    pub store_as_string: Option<bool>,
+    /// Only the numbers at these paths are parsed as `BigNumber` or `BigInt`, as
+    /// with `always_parse_as_big` and `parse_float_as_big`; all other numbers are
+    /// a `number`. Paths are JSON pointers like `/orders/0/price` or dotted like
+    /// `orders[].price`, and `*` matches any key or index.
+    pub big_number_paths: Option<Vec<String>>,
+    /// Return every number as a `LosslessNumber` holding its exact text, unless
+    /// `number_types` gives it a type.
+    pub lossless_numbers: Option<bool>,
+    /// Return every number as a string of its exact text, unless `number_types`
+    /// gives it a type.
+    pub numbers_as_strings: Option<bool>,
+    /// What to do when a number that becomes a `number` does not have exactly
+    /// the value of its text, `"ignore"` by default.
+    pub precision_loss: Option<PrecisionLoss>,
+    /// What to do with a number too large for a `number`, e.g. `1e400`, `"null"`
+    /// by default.
+    pub float_overflow: Option<FloatOverflow>,
+    /// Return arrays of at least 16 numbers as a `Float64Array`, or as a
+    /// `BigInt64Array` if they are integers outside the safe range. Only used when
+    /// no other number option is set, and not with a reviver, `freeze` or `sendable`.
+    pub typed_arrays: Option<bool>,
+    /// The type of the numbers at each path, e.g. `{ "orders[].qty": "int" }`.
+    /// Paths are written as in `big_number_paths` and take precedence over it.
+    pub number_types: Option<HashMap<String, NumberType>>,
+    /// Maximum nesting depth of arrays and objects.
+    pub max_depth: Option<u32>,
+    /// Maximum number of values in the document, counting every array, object,
+    /// element and property value.
+    pub max_nodes: Option<u32>,
+    /// Maximum size of the input in UTF-8 bytes.
+    pub max_input_bytes: Option<u32>,
+    /// Maximum length of a string or key in UTF-16 code units.
+    pub max_string_length: Option<u32>,
+    /// What to do with `__proto__` keys, `"error"` by default, or `"preserve"`
+    /// with `objects_as_maps`.
+    pub proto_action: Option<KeyAction>,
+    /// What to do with `constructor` and `prototype` keys, `"preserve"` by default.
+    pub constructor_action: Option<KeyAction>,
+    /// Renames the keys of objects to a `KeyCase`, e.g. `"camel"` for `userId`
+    /// from `user_id`. Paths in the other options, `proto_action` and
+    /// `constructor_action` see the renamed keys.
+    pub key_transform: Option<KeyCase>,
+    /// Fail on a UTF-8 byte order mark at the start of the input instead of
+    /// skipping it.
+    pub reject_bom: Option<bool>,
+    /// Reject anything RFC 8259 does not allow. Overrides `json5` and every
+    /// `allow_*` option.
+    pub strict: Option<bool>,
+    /// Accept `NaN`, `Infinity` and `-Infinity`.
+    pub allow_non_finite: Option<bool>,
+    /// Skip `// line` and `/* block */` comments like whitespace.
+    pub allow_comments: Option<bool>,
+    /// Accept a comma after the last element of an array or object.
+    pub allow_trailing_commas: Option<bool>,
+    /// Accept unescaped U+0000 to U+001F in strings, which RFC 8259 forbids.
+    pub allow_control_characters: Option<bool>,
+    /// Accept any Unicode whitespace between tokens, e.g. form feed or U+00A0,
+    /// not only space, tab, CR and LF like `JSON.parse`.
+    pub allow_unicode_whitespace: Option<bool>,
+    /// Accept strings and keys in single quotes, e.g. `{'a': 'b'}`, where `\'`
+    /// escapes a quote.
+    pub allow_single_quotes: Option<bool>,
+    /// Accept `0x1A`, `0b101` and `0o17` integers. They follow the integer
+    /// options, so a long hex hash becomes a BigNumber or BigInt without loss.
+    pub allow_radix_literals: Option<bool>,
+    /// Parse JSON5: unquoted keys, single-quoted strings, hex numbers, leading
+    /// `+`, multi-line strings, comments, trailing commas, `NaN` and `Infinity`.
+    pub json5: Option<bool>,
+    /// What to do with a `\u` escaped surrogate that is not part of a pair,
+    /// `"error"` by default.
+    pub lone_surrogates: Option<LoneSurrogates>,
+    /// Called with the text of every number and the keys and indices leading
+    /// to it. The result is used as the value, unless it is `undefined`.
+    /// Not supported by `parseAsync`.
+    pub number_handler: Option<NumberHandler>,
+    /// Create a `Map` for every object instead of a plain object.
+    pub objects_as_maps: Option<bool>,
+    /// `Object.freeze` every array and object. The entries of a `Map` stay mutable.
+    pub freeze: Option<bool>,
+    /// Create every array and object as an ArkTS Sendable container, so a value
+    /// parsed in a TaskPool worker can be passed to the main thread without a
+    /// copy. A number that would be a BigNumber or LosslessNumber is an error.
+    /// Not with a reviver, `objects_as_maps`, `freeze` or `parse_dates`.
+    pub sendable: Option<bool>,
+    /// Replace every object of the form `{"$ref": pointer}`, as written by
+    /// `stringify` with `refs`, with the value at the JSON pointer `pointer`, so
+    /// shared and circular references are restored. A pointer that leads nowhere
+    /// is left as it is. Not with `objects_as_maps`, `freeze` or `sendable`.
+    pub resolve_refs: Option<bool>,
+    /// Return ISO 8601 date-time strings like `2024-05-01T12:00:00Z` as `Date`s.
+    pub parse_dates: Option<bool>,
+    /// A regular expression that replaces the ISO 8601 check of `parse_dates`.
+    /// Matching strings are passed to `new Date()`.
+    pub date_pattern: Option<String>,
+    /// Only convert dates at these paths, written like `big_number_paths`.
+    pub date_paths: Option<Vec<String>>,
+    /// Called by `parseAsync` as the parser advances through the input, about a
+    /// hundred times for a large document.
+    pub on_progress: Option<ProgressCallback>,
+}
+
+/// How the parser handles an object key that could be used for prototype pollution.
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Throw an error.
+    Error,
+    /// Leave the property out of the object.
+    Ignore,
+    /// Keep it as an own property, without touching the prototype.
+    Preserve,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoneSurrogates {
+    /// Throw an error.
+    Error,
+    /// Replace it with U+FFFD.
+    Replace,
+    /// Keep the code unit in the JS string, like `JSON.parse`.
+    Preserve,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecisionLoss {
+    /// Keep the nearest `number`.
+    Ignore,
+    /// Throw an error at the number.
+    Error,
+    /// Keep the nearest `number` and `console.warn` the text and path.
+    Warn,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatOverflow {
+    /// Return `null`.
+    Null,
+    /// Throw an error at the number.
+    Error,
+    /// Return `Infinity` or `-Infinity`, like `JSON.parse`.
+    Infinity,
+    /// Return a BigNumber with the exact value.
+    BigNumber,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberType {
+    /// A `number`, possibly losing precision.
+    Number,
+    /// An integer `number`. Other numbers are an error.
+    Int,
+    /// A `BigInt`. Other numbers are an error.
+    Bigint,
+    /// A `BigNumber`.
+    Decimal,
+    /// The original text.
+    String,
 }
@@ -1,300 +1,2144 @@
-use std::str::Chars;
-use std::{iter::Peekable, str::FromStr};
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
 
-use bigdecimal::num_bigint::Sign;
-use bigdecimal::{BigDecimal, Num};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, Num, ToPrimitive};
 use napi_derive_ohos::napi;
-use napi_ohos::bindgen_prelude::ToNapiValue;
-use napi_ohos::{Env, Error, JsString, JsUnknown, NapiValue, Result, Status};
-use widestring::Utf16String;
+use napi_ohos::bindgen_prelude::{
+    AbortSignal, ArrayBuffer, AsyncTask, Either, Either3, FnArgs, Function, Uint8Array,
+};
+use napi_ohos::threadsafe_function::ThreadsafeFunctionCallMode;
+use napi_ohos::{Env, Error, JsUnknown, Result, Status, Task};
+use regex::Regex;
+use widestring::U16String;
 
-use crate::bignumber::BigNumber;
-use crate::error::ParseError;
-use crate::options::Options;
+use crate::builder::{
+    Builder, CONSTRUCTOR_KEY, JsBuilder, Number, NumberArray, PROTO_KEY, PROTOTYPE_KEY, Reviver,
+};
+use crate::error::{JsonErrorCode, ParseError, ParseResult, PositionedError, with_code};
+use crate::options::{
+    FloatOverflow, KeyAction, LoneSurrogates, NumberType, Options, PrecisionLoss, ProgressCallback,
+};
+use crate::path::{Path, PathSegment};
+use crate::value::{JsonValue, ValueBuilder};
 
 #[napi]
 #[allow(dead_code)]
-pub fn parse(env: Env, s: String, options: Option<Options>) -> Result<JsUnknown> {
+pub fn parse(
+    env: Env,
+    s: String,
+    options: Option<Options>,
+    reviver: Option<Reviver<'_>>,
+) -> Result<JsUnknown> {
     let opts = options.unwrap_or_default();
-    let mut parser = JsonParser::new(&s, opts, env);
-    parser.parse()
+    let builder = JsBuilder::new(&env, &opts, reviver)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+    let mut parser = JsonParser::new(s.as_bytes(), &opts, builder);
+    parser.parse().map_err(|e| e.into_error(&env))
 }
 
-pub struct JsonParser<'a> {
-    chars: Peekable<Chars<'a>>,
-    opts: Options,
+#[napi]
+#[allow(dead_code)]
+pub fn parse_buffer(
     env: Env,
+    buf: Either<ArrayBuffer, Uint8Array>,
+    options: Option<Options>,
+    reviver: Option<Reviver<'_>>,
+) -> Result<JsUnknown> {
+    let bytes: &[u8] = match &buf {
+        Either::A(b) => b,
+        Either::B(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let builder = JsBuilder::new(&env, &opts, reviver)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+    let mut parser = JsonParser::new(bytes, &opts, builder);
+    parser.parse().map_err(|e| e.into_error(&env))
 }
 
-impl<'a> JsonParser<'a> {
-    pub fn new(input: &'a str, opts: Options, env: Env) -> Self {
+/// Parses a document split into UTF-8 `chunks`, e.g. read from a file piece by
+/// piece, as if they were one buffer. The chunks are not copied into one
+/// buffer, only a token that crosses from one chunk into the next is.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_chunks(
+    env: Env,
+    chunks: Vec<Either<ArrayBuffer, Uint8Array>>,
+    options: Option<Options>,
+    reviver: Option<Reviver<'_>>,
+) -> Result<JsUnknown> {
+    let chunks: Vec<&[u8]> = chunks
+        .iter()
+        .map(|chunk| match chunk {
+            Either::A(b) => &b[..],
+            Either::B(b) => &b[..],
+        })
+        .collect();
+    let opts = options.unwrap_or_default();
+    let builder = JsBuilder::new(&env, &opts, reviver)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+    let mut parser = JsonParser::from_chunks(&chunks, &opts, builder);
+    parser.parse().map_err(|e| e.into_error(&env))
+}
+
+/// `(value: unknown, index: number) => void`
+type RecordCallback<'a> = Function<'a, FnArgs<(JsUnknown, u32)>, JsUnknown>;
+
+/// Parses newline-delimited JSON. Blank lines are skipped. Returns the records as
+/// an array, or passes each one to `callback` and returns `undefined`.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_lines(
+    env: Env,
+    input: Either3<String, ArrayBuffer, Uint8Array>,
+    options: Option<Options>,
+    callback: Option<RecordCallback<'_>>,
+) -> Result<JsUnknown> {
+    let bytes: &[u8] = match &input {
+        Either3::A(s) => s.as_bytes(),
+        Either3::B(b) => b,
+        Either3::C(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let mut records = env.create_empty_array()?;
+    let mut index = 0;
+    let mut line_start = 0;
+    for line in bytes.split(|&c| c == b'\n') {
+        let offset = line_start;
+        line_start += line.len() + 1;
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let builder = JsBuilder::new(&env, &opts, None)
+            .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+        let mut parser = JsonParser::new(line, &opts, builder);
+        let value = parser.parse().map_err(|e| {
+            // Report the position in the whole input rather than in the line.
+            let mut e = PositionedError::new(e.error, bytes, offset + e.position.offset);
+            e.record = Some(index as usize);
+            e.into_error(&env)
+        })?;
+        match &callback {
+            Some(callback) => {
+                callback.call((value, index).into())?;
+            }
+            None => records.set_element(index, value)?,
+        }
+        index += 1;
+    }
+
+    match callback {
+        Some(_) => Ok(env.get_undefined()?.into_unknown()),
+        None => Ok(records.into_unknown()),
+    }
+}
+
+/// The record separator that starts each text of a JSON text sequence.
+const RS: u8 = 0x1E;
+
+/// Parses a JSON text sequence (RFC 7464, `application/json-seq`). Records that
+/// fail to parse, including numbers and literals that may have been truncated,
+/// are skipped and parsing resumes at the next record separator. Returns the
+/// records as an array, or passes each one to `callback` with its position in
+/// the sequence and returns `undefined`.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_seq(
+    env: Env,
+    input: Either3<String, ArrayBuffer, Uint8Array>,
+    options: Option<Options>,
+    callback: Option<RecordCallback<'_>>,
+) -> Result<JsUnknown> {
+    let bytes: &[u8] = match &input {
+        Either3::A(s) => s.as_bytes(),
+        Either3::B(b) => b,
+        Either3::C(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let mut records = env.create_empty_array()?;
+    let mut count = 0;
+    // Anything before the first separator is not a record.
+    for (index, record) in bytes.split(|&c| c == RS).skip(1).enumerate() {
+        if is_truncated(record) {
+            continue;
+        }
+
+        let builder = JsBuilder::new(&env, &opts, None)
+            .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+        let mut parser = JsonParser::new(record, &opts, builder);
+        let value = match parser.parse() {
+            Ok(value) => value,
+            Err(e) if matches!(e.error, ParseError::NapiError(_)) => {
+                return Err(e.into_error(&env));
+            }
+            Err(_) => continue,
+        };
+        match &callback {
+            Some(callback) => {
+                callback.call((value, index as u32).into())?;
+            }
+            None => records.set_element(count, value)?,
+        }
+        count += 1;
+    }
+
+    match callback {
+        Some(_) => Ok(env.get_undefined()?.into_unknown()),
+        None => Ok(records.into_unknown()),
+    }
+}
+
+/// Whether a record is a number, `true`, `false` or `null` that does not end in
+/// whitespace, so it may have been cut short (RFC 7464 section 2.4).
+fn is_truncated(record: &[u8]) -> bool {
+    let Some(first) = record.iter().find(|c| !c.is_ascii_whitespace()) else {
+        return false;
+    };
+    matches!(first, b'0'..=b'9' | b'-' | b't' | b'f' | b'n')
+        && !record.last().is_some_and(u8::is_ascii_whitespace)
+}
+
+/// Parses a document whose root is an array and passes each element to
+/// `callback` as soon as it is complete. The array itself is never created, and
+/// each element can be collected once `callback` returns.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_elements(
+    env: Env,
+    input: Either3<String, ArrayBuffer, Uint8Array>,
+    callback: RecordCallback<'_>,
+    options: Option<Options>,
+) -> Result<()> {
+    let bytes: &[u8] = match &input {
+        Either3::A(s) => s.as_bytes(),
+        Either3::B(b) => b,
+        Either3::C(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let builder = JsBuilder::new(&env, &opts, None)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?
+        .without_key_cache();
+    let mut parser = JsonParser::new(bytes, &opts, builder);
+    let mut index = 0;
+    loop {
+        // Release the handles of each element before parsing the next one.
+        let done = env.run_in_scope(|| match parser.next_element() {
+            Ok(Some(value)) => {
+                callback.call((value, index).into())?;
+                Ok(false)
+            }
+            Ok(None) => Ok(true),
+            Err(e) => Err(e.into_error(&env)),
+        })?;
+        if done {
+            return Ok(());
+        }
+        index += 1;
+    }
+}
+
+#[napi]
+#[allow(dead_code)]
+pub fn parse_async(
+    s: String,
+    options: Option<Options>,
+    signal: Option<AbortSignal>,
+) -> Result<AsyncTask<ParseTask>> {
+    ParseTask::spawn(Source::Text(s), options, signal)
+}
+
+/// Reads and parses a file on a worker thread, so its contents never have to be
+/// passed in as a JS string.
+#[napi]
+#[allow(dead_code)]
+pub fn parse_file(
+    path: String,
+    options: Option<Options>,
+    signal: Option<AbortSignal>,
+) -> Result<AsyncTask<ParseTask>> {
+    ParseTask::spawn(Source::File(PathBuf::from(path)), options, signal)
+}
+
+/// Where a `ParseTask` gets its input.
+enum Source {
+    Text(String),
+    File(PathBuf),
+}
+
+/// Parses on a worker thread, then creates the JS values on the JS thread.
+pub struct ParseTask {
+    source: Source,
+    opts: Options,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ParseTask {
+    fn spawn(
+        source: Source,
+        options: Option<Options>,
+        signal: Option<AbortSignal>,
+    ) -> Result<AsyncTask<ParseTask>> {
+        let opts = options.unwrap_or_default();
+        check_value_options(&opts, "async parsing")?;
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Some(signal) = &signal {
+            // napi only cancels work that has not started yet, so also tell the parser.
+            let cancelled = cancelled.clone();
+            signal.on_abort(move || cancelled.store(true, Ordering::Relaxed));
+        }
+        let task = ParseTask {
+            source,
+            opts,
+            cancelled,
+        };
+        Ok(AsyncTask::with_optional_signal(task, signal))
+    }
+}
+
+/// Rejects the options that need the JS thread while values are parsed, for
+/// `api`, which parses into a `JsonValue`.
+pub fn check_value_options(opts: &Options, api: &str) -> Result<()> {
+    if opts.number_handler.is_some() {
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("numberHandler is not supported by {api}"),
+        ));
+    }
+    if opts.precision_loss == Some(PrecisionLoss::Warn) {
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("precisionLoss \"warn\" is not supported by {api}"),
+        ));
+    }
+    Ok(())
+}
+
+fn read_error(path: &std::path::Path, e: io::Error) -> Error {
+    Error::new(
+        Status::GenericFailure,
+        format!("Failed to read {}: {e}", path.display()),
+    )
+}
+
+impl Task for ParseTask {
+    /// The error is kept until `resolve` so it can be thrown with its position.
+    type Output = std::result::Result<JsonValue, PositionedError>;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let contents;
+        let input = match &self.source {
+            Source::Text(s) => s.as_bytes(),
+            Source::File(path) => {
+                // Check the limit before reading rather than after.
+                let len = fs::metadata(path).map_err(|e| read_error(path, e))?.len();
+                if let Some(max) = self.opts.max_input_bytes
+                    && len > max as u64
+                {
+                    let error = PositionedError::new(ParseError::InputTooLarge, &[], 0);
+                    return Ok(Err(error));
+                }
+                contents = fs::read(path).map_err(|e| read_error(path, e))?;
+                &contents[..]
+            }
+        };
+        let mut parser =
+            JsonParser::new(input, &self.opts, ValueBuilder).with_cancel_flag(&self.cancelled);
+        if let Some(callback) = &self.opts.on_progress {
+            parser = parser.with_progress(callback);
+        }
+        Ok(parser.parse())
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<JsUnknown> {
+        let value = output.map_err(|e| e.into_error(&env))?;
+        let build = || -> ParseResult<JsUnknown> {
+            let mut builder = JsBuilder::new(&env, &self.opts, None)?;
+            value.build(&mut builder)
+        };
+        build().map_err(|e| with_code(&env, e.into(), JsonErrorCode::Internal))
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The shortest array `typed_arrays` applies to.
+const MIN_TYPED_ARRAY_LEN: usize = 16;
+
+/// Progress is reported at most once per this many bytes.
+const MIN_PROGRESS_STEP: usize = 64 * 1024;
+
+/// `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: i64 = 9_007_199_254_740_991;
+
+/// The strings `parse_dates` converts without a `date_pattern`.
+static ISO_DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}(:\d{2}(\.\d+)?)?(Z|[+-]\d{2}:\d{2})?$").unwrap()
+});
+
+/// How a number is converted, see `JsonParser::precision`.
+struct Precision {
+    /// `always_parse_as_big`
+    always_big: bool,
+    /// `parse_float_as_big`
+    float_big: bool,
+    store_as_string: bool,
+    /// Outside `big_number_paths`, so always a `number`.
+    plain: bool,
+}
+
+/// How far `JsonParser::next_element` has got through the root array.
+#[derive(Clone, Copy)]
+enum Elements {
+    NotStarted,
+    /// The index of the next element.
+    At(u32),
+    Done,
+}
+
+/// An array or object whose elements are still being parsed. An object frame
+/// also holds the key of the value being parsed, or `None` if it is dropped.
+enum Frame<A, O> {
+    Array(A),
+    Object(O, Option<U16String>),
+}
+
+/// What `JsonParser::parse_value` parses next.
+#[derive(Clone, Copy)]
+enum Step {
+    Value,
+    /// An element or the end of the array, after `[`, or after a comma with
+    /// `allow_trailing_commas`.
+    ElementOrEnd,
+    /// A key or the end of the object, after `{`, or after a comma with
+    /// `allow_trailing_commas`.
+    KeyOrEnd,
+    Key,
+    /// A comma or the end of the innermost array or object, after a value.
+    CommaOrEnd,
+}
+
+/// What `JsonParser` prepares from the options before parsing, so that it can
+/// be kept for parsing more documents with the same options.
+#[derive(Clone)]
+pub struct Compiled {
+    /// Whether `path` is kept up to date, which costs a key copy per member.
+    track_path: bool,
+    big_number_paths: Option<Vec<Path>>,
+    number_types: Option<Vec<(Path, NumberType)>>,
+    /// The strings to return as dates, or the error compiling `date_pattern`.
+    date_pattern: Option<std::result::Result<Regex, regex::Error>>,
+    date_paths: Option<Vec<Path>>,
+    typed_arrays: bool,
+}
+
+impl Compiled {
+    pub fn new(opts: &Options) -> Self {
+        Compiled {
+            track_path: opts.number_handler.is_some()
+                || opts.big_number_paths.is_some()
+                || opts.number_types.is_some()
+                || opts.date_paths.is_some()
+                || opts.precision_loss == Some(PrecisionLoss::Warn),
+            big_number_paths: opts
+                .big_number_paths
+                .as_ref()
+                .map(|paths| paths.iter().map(|p| Path::parse(p)).collect()),
+            number_types: opts
+                .number_types
+                .as_ref()
+                .map(|types| types.iter().map(|(p, t)| (Path::parse(p), *t)).collect()),
+            date_pattern: opts
+                .parse_dates
+                .is_some_and(|e| e)
+                .then(|| match &opts.date_pattern {
+                    Some(pattern) => Regex::new(pattern),
+                    None => Ok(ISO_DATE_TIME.clone()),
+                }),
+            date_paths: opts
+                .date_paths
+                .as_ref()
+                .map(|paths| paths.iter().map(|p| Path::parse(p)).collect()),
+            // A typed array can be neither frozen nor Sendable.
+            typed_arrays: opts.typed_arrays.is_some_and(|e| e)
+                && plain_numbers(opts)
+                && !opts.freeze.is_some_and(|e| e)
+                && !opts.sendable.is_some_and(|e| e),
+        }
+    }
+
+    /// Checks the options that can be invalid.
+    pub fn check(&self) -> ParseResult<()> {
+        match &self.date_pattern {
+            Some(Err(e)) => Err(ParseError::NapiError(Error::new(
+                Status::InvalidArg,
+                format!("Invalid datePattern: {e}"),
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parses UTF-8 input byte by byte. Only string contents are decoded, everything
+/// else in the grammar is ASCII.
+pub struct JsonParser<'a, B: Builder> {
+    /// The chunk being parsed, or a copy of a token that crosses into the next
+    /// chunk followed by that chunk.
+    input: Cow<'a, [u8]>,
+    pos: usize,
+    /// The chunks after `input`, for `from_chunks`.
+    rest: &'a [&'a [u8]],
+    /// The length of the whole input.
+    len: usize,
+    /// The offset of `input` in the whole input.
+    base: usize,
+    /// The newlines before `input`, and the characters after the last of them.
+    line_base: usize,
+    column_base: usize,
+    /// Set while a token is parsed, when `input` must not move on to the next chunk.
+    in_token: bool,
+    /// Set while more input may be pushed after `input`, see `push`.
+    more: bool,
+    /// Set when parsing stopped because pushed input ran out.
+    suspended: bool,
+    opts: &'a Options,
+    compiled: Cow<'a, Compiled>,
+    builder: B,
+    /// The arrays and objects around the value being parsed.
+    stack: Vec<Frame<B::Array, B::Object>>,
+    step: Step,
+    /// Where the value being parsed is, for `Builder::raw_number` and `big_number_paths`.
+    path: Vec<PathSegment>,
+    elements: Elements,
+    /// The values parsed so far, for `max_nodes`.
+    nodes: usize,
+    /// Nesting around the value being parsed that `parse_value` does not see.
+    depth_offset: usize,
+    /// Set from another thread to stop parsing.
+    cancelled: Option<&'a AtomicBool>,
+    progress: Option<&'a ProgressCallback>,
+    /// The position at which progress is reported next.
+    next_progress: usize,
+}
+
+/// Where parsing of input pushed to `JsonParser::push` stopped.
+pub struct Partial<B: Builder> {
+    /// The token cut off by the end of the input pushed so far.
+    tail: Vec<u8>,
+    scan: TokenScan,
+    /// The length of the input pushed so far.
+    len: usize,
+    /// The offset of `tail` in the input, and the newlines and characters before it.
+    base: usize,
+    line_base: usize,
+    column_base: usize,
+    /// Whether a BOM has been looked for and the input checked.
+    started: bool,
+    stack: Vec<Frame<B::Array, B::Object>>,
+    step: Step,
+    path: Vec<PathSegment>,
+    nodes: usize,
+    /// The root value, once it has been parsed.
+    root: Option<B::Value>,
+}
+
+impl<B: Builder> Default for Partial<B> {
+    fn default() -> Self {
+        Partial {
+            tail: Vec::new(),
+            scan: TokenScan::default(),
+            len: 0,
+            base: 0,
+            line_base: 0,
+            column_base: 0,
+            started: false,
+            stack: Vec::new(),
+            step: Step::Value,
+            path: Vec::new(),
+            nodes: 0,
+            root: None,
+        }
+    }
+}
+
+impl<'a, B: Builder> JsonParser<'a, B> {
+    pub fn new(input: &'a [u8], opts: &'a Options, builder: B) -> Self {
+        Self::with_compiled(input, opts, Cow::Owned(Compiled::new(opts)), builder)
+    }
+
+    /// Uses `compiled`, which must have been created from `opts`.
+    pub fn with_compiled(
+        input: &'a [u8],
+        opts: &'a Options,
+        compiled: Cow<'a, Compiled>,
+        builder: B,
+    ) -> Self {
         JsonParser {
-            chars: input.chars().peekable(),
+            input: Cow::Borrowed(input),
+            pos: 0,
+            rest: &[],
+            len: input.len(),
+            base: 0,
+            line_base: 0,
+            column_base: 0,
+            in_token: false,
+            more: false,
+            suspended: false,
             opts,
-            env,
+            compiled,
+            builder,
+            stack: Vec::new(),
+            step: Step::Value,
+            path: Vec::new(),
+            elements: Elements::NotStarted,
+            nodes: 0,
+            depth_offset: 0,
+            cancelled: None,
+            progress: None,
+            next_progress: usize::MAX,
+        }
+    }
+
+    /// Parses the concatenation of `chunks` without copying them into one
+    /// buffer. Only a token that crosses from one chunk into the next is copied.
+    pub fn from_chunks(chunks: &'a [&'a [u8]], opts: &'a Options, builder: B) -> Self {
+        let (first, rest) = chunks
+            .split_first()
+            .map_or((&[][..], &[][..]), |(c, r)| (*c, r));
+        let mut parser = Self::new(first, opts, builder);
+        parser.rest = rest;
+        parser.len = chunks.iter().map(|c| c.len()).sum();
+        parser
+    }
+
+    /// Reports how much of the input has been consumed to `callback`.
+    pub fn with_progress(mut self, callback: &'a ProgressCallback) -> Self {
+        self.progress = Some(callback);
+        self.next_progress = self.progress_step();
+        self
+    }
+
+    fn progress_step(&self) -> usize {
+        (self.len / 100).max(MIN_PROGRESS_STEP)
+    }
+
+    fn report_progress(&mut self) {
+        if let Some(callback) = self.progress {
+            let offset = self.base + self.pos;
+            let args = (offset as f64, self.len as f64);
+            callback.call(args.into(), ThreadsafeFunctionCallMode::NonBlocking);
+            self.next_progress = offset + self.progress_step();
+        }
+    }
+
+    /// Stops parsing with `ParseError::Cancelled` once `flag` is set.
+    pub fn with_cancel_flag(mut self, flag: &'a AtomicBool) -> Self {
+        self.cancelled = Some(flag);
+        self
+    }
+
+    pub fn parse(&mut self) -> std::result::Result<B::Value, PositionedError> {
+        self.parse_document()
+            .map_err(|error| self.positioned(error))
+    }
+
+    /// Adds the position of the error in the whole input.
+    fn positioned(&self, error: ParseError) -> PositionedError {
+        let mut e = PositionedError::new(error, &self.input, self.pos);
+        if e.position.line == 1 {
+            e.position.column += self.column_base;
         }
+        e.position.line += self.line_base;
+        e.position.offset += self.base;
+        e
+    }
+
+    fn parse_document(&mut self) -> ParseResult<B::Value> {
+        self.begin()?;
+        let value = self.parse_value()?;
+        self.expect_end()?;
+        self.report_progress();
+        self.builder.finish(value)
+    }
+
+    /// Parses the next element of a document whose root is an array, without
+    /// building the array. Returns `None` once the array has been closed.
+    pub fn next_element(&mut self) -> std::result::Result<Option<B::Value>, PositionedError> {
+        self.parse_next_element()
+            .map_err(|error| self.positioned(error))
     }
 
-    pub fn parse(&mut self) -> Result<JsUnknown> {
+    fn parse_next_element(&mut self) -> ParseResult<Option<B::Value>> {
+        let index = match self.elements {
+            Elements::Done => return Ok(None),
+            Elements::NotStarted => {
+                self.begin()?;
+                self.skip_whitespace()?;
+                self.check_depth(1)?;
+                self.expect_byte(b'[')?;
+                self.depth_offset = 1;
+                self.skip_whitespace()?;
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return self.end_elements();
+                }
+                0
+            }
+            Elements::At(index) => {
+                self.skip_whitespace()?;
+                match self.next() {
+                    Some(b',') if !self.skip_trailing_comma(b']')? => index,
+                    Some(b',' | b']') => return self.end_elements(),
+                    Some(_) => {
+                        self.pos -= 1;
+                        return Err(ParseError::ExpectedCommaOrEnd);
+                    }
+                    None => return Err(ParseError::UnexpectedEndOfInput),
+                }
+            }
+        };
+
+        if self.compiled.track_path {
+            self.path.clear();
+            self.path.push(PathSegment::Index(index));
+        }
         let value = self.parse_value()?;
-        self.skip_whitespace();
-        if self.chars.peek().is_some() {
-            return Err(ParseError::TrailingCharacters.into());
+        self.elements = Elements::At(index + 1);
+        Ok(Some(value))
+    }
+
+    fn end_elements(&mut self) -> ParseResult<Option<B::Value>> {
+        self.elements = Elements::Done;
+        self.expect_end()?;
+        Ok(None)
+    }
+
+    /// Parses `chunk` as the next piece of an input that is pushed piece by
+    /// piece, going on from where `partial` stopped. Errors are returned by the
+    /// push that has the input causing them, and the root value by the `last`
+    /// push. Only a token cut off by the end of `chunk` is copied, to be parsed
+    /// once a later push may end it.
+    pub fn push(
+        partial: &mut Partial<B>,
+        chunk: &'a [u8],
+        last: bool,
+        opts: &'a Options,
+        compiled: &'a Compiled,
+        builder: B,
+    ) -> std::result::Result<Option<B::Value>, PositionedError> {
+        partial.len += chunk.len();
+        let too_large = opts
+            .max_input_bytes
+            .is_some_and(|max| partial.len > max as usize);
+        let input = if partial.tail.is_empty() {
+            Cow::Borrowed(chunk)
+        } else {
+            partial.tail.extend_from_slice(chunk);
+            if !last && !too_large && !partial.scan.may_end(&partial.tail) {
+                return Ok(None);
+            }
+            Cow::Owned(std::mem::take(&mut partial.tail))
+        };
+
+        let mut parser = Self::with_compiled(&[], opts, Cow::Borrowed(compiled), builder);
+        parser.input = input;
+        parser.len = partial.len;
+        parser.more = !last;
+        parser.base = partial.base;
+        parser.line_base = partial.line_base;
+        parser.column_base = partial.column_base;
+        parser.stack = std::mem::take(&mut partial.stack);
+        parser.step = partial.step;
+        parser.path = std::mem::take(&mut partial.path);
+        parser.nodes = partial.nodes;
+        let result = parser
+            .parse_pushed(partial)
+            .map_err(|error| parser.positioned(error))?;
+
+        // A token that starts later than the last one has not been scanned yet.
+        let pos = parser.pos;
+        if pos > 0 {
+            partial.scan = TokenScan::default();
+        }
+        parser.consume(pos);
+        partial.tail = match parser.input {
+            Cow::Owned(mut buf) => {
+                buf.drain(..pos);
+                buf
+            }
+            Cow::Borrowed(bytes) => bytes[pos..].to_vec(),
+        };
+        partial.base = parser.base;
+        partial.line_base = parser.line_base;
+        partial.column_base = parser.column_base;
+        partial.stack = parser.stack;
+        partial.step = parser.step;
+        partial.path = parser.path;
+        partial.nodes = parser.nodes;
+        Ok(result)
+    }
+
+    fn parse_pushed(&mut self, partial: &mut Partial<B>) -> ParseResult<Option<B::Value>> {
+        if let Some(max) = self.opts.max_input_bytes
+            && self.len > max as usize
+        {
+            // Point at the first byte past the limit.
+            self.pos = (max as usize)
+                .saturating_sub(self.base)
+                .min(self.input.len());
+            return Err(ParseError::InputTooLarge);
+        }
+        if !partial.started {
+            // Wait until a BOM can be told apart from the start of the value.
+            if self.more && UTF8_BOM.starts_with(&self.input[..]) {
+                return Ok(None);
+            }
+            self.begin()?;
+            partial.started = true;
+        }
+        if partial.root.is_none() {
+            match self.parse_value() {
+                Ok(value) => partial.root = Some(value),
+                Err(_) if self.suspended => return Ok(None),
+                Err(e) => return Err(e),
+            }
+        }
+        match self.expect_end() {
+            Ok(()) => {}
+            Err(_) if self.suspended => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        match partial.root.take() {
+            Some(root) if !self.more => self.builder.finish(root).map(Some),
+            root => {
+                partial.root = root;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Checks the input and options before the first value, and skips a BOM.
+    fn begin(&mut self) -> ParseResult<()> {
+        self.check_input_size()?;
+        self.compiled.check()?;
+        if self.pos == 0 && self.input.starts_with(UTF8_BOM) {
+            if self.opts.reject_bom.is_some_and(|e| e) {
+                return Err(ParseError::UnexpectedCharacter('\u{FEFF}'));
+            }
+            self.pos = UTF8_BOM.len();
+        }
+        Ok(())
+    }
+
+    fn check_input_size(&self) -> ParseResult<()> {
+        match self.opts.max_input_bytes {
+            Some(max) if self.len > max as usize => Err(ParseError::InputTooLarge),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that only whitespace follows the root value.
+    fn expect_end(&mut self) -> ParseResult<()> {
+        self.skip_whitespace()?;
+        if self.peek().is_some() {
+            return Err(ParseError::TrailingCharacters);
+        }
+        Ok(())
+    }
+
+    /// With `typed_arrays`, parses an array of at least `MIN_TYPED_ARRAY_LEN`
+    /// numbers at the current position in one go. Returns `None`, consuming
+    /// nothing, for any other array.
+    fn scan_number_array(&mut self) -> ParseResult<Option<NumberArray>> {
+        if !self.compiled.typed_arrays || !self.builder.wants_number_arrays() {
+            return Ok(None);
+        }
+        let start = self.pos;
+        // The array is scanned again from `start` if it is cut off by the end of the chunk.
+        let in_token = std::mem::replace(&mut self.in_token, true);
+        let values = self.scan_numbers();
+        self.in_token = in_token;
+        if values.is_none() {
+            self.pos = start;
+        }
+        Ok(values)
+    }
+
+    fn scan_numbers(&mut self) -> Option<NumberArray> {
+        self.pos += 1;
+        let mut floats = Vec::new();
+        // The same numbers, as long as they are all integers.
+        let mut ints = Some(Vec::new());
+        let mut unsafe_int = false;
+        loop {
+            self.skip_whitespace().ok()?;
+            let start = self.pos;
+            while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.peek() {
+                self.pos += 1;
+            }
+            let bytes = &self.input[start..self.pos];
+            if invalid_number_at(bytes, false).is_some() {
+                return None;
+            }
+            let text = self.number_text(start);
+            let v = text.parse::<f64>().ok().filter(|v| v.is_finite())?;
+            let int = text.parse::<i64>().ok();
+            if let Some(i) = int
+                && !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&i)
+            {
+                unsafe_int = true;
+            }
+            match (&mut ints, int) {
+                (Some(ints), Some(i)) => ints.push(i),
+                // Too big even for a `BigInt64Array`.
+                (_, None) if !bytes.iter().any(|c| matches!(c, b'.' | b'e' | b'E')) => {
+                    return None;
+                }
+                _ => ints = None,
+            }
+            floats.push(v);
+
+            self.skip_whitespace().ok()?;
+            match self.next() {
+                Some(b',') => {}
+                Some(b']') => break,
+                _ => return None,
+            }
+        }
+
+        if floats.len() < MIN_TYPED_ARRAY_LEN {
+            return None;
+        }
+        match ints {
+            Some(ints) if unsafe_int => Some(NumberArray::BigInt64(ints)),
+            // Unsafe integers mixed with other numbers would lose precision.
+            _ if unsafe_int => None,
+            _ => Some(NumberArray::Float64(floats)),
+        }
+    }
+
+    /// Parses one value. Nesting is tracked on an explicit stack rather than by
+    /// recursion, so deeply nested input cannot overflow the native stack. The
+    /// stack and the next `step` are kept in the parser, so that parsing can
+    /// stop where pushed input runs out and go on with the next push.
+    fn parse_value(&mut self) -> ParseResult<B::Value> {
+        loop {
+            if let Some(cancelled) = self.cancelled
+                && cancelled.load(Ordering::Relaxed)
+            {
+                return Err(ParseError::Cancelled);
+            }
+            if self.base + self.pos >= self.next_progress {
+                self.report_progress();
+            }
+            self.skip_whitespace()?;
+            let Some(c) = self.peek() else {
+                return Err(self.end_of_input());
+            };
+            let value = match (self.step, c) {
+                (Step::Value, _) => match self.begin_value(c)? {
+                    Some(value) => value,
+                    None => continue,
+                },
+                (Step::ElementOrEnd, b']') | (Step::KeyOrEnd, b'}') => {
+                    self.pos += 1;
+                    self.end_container()?
+                }
+                (Step::ElementOrEnd, _) => {
+                    self.step = Step::Value;
+                    continue;
+                }
+                (Step::KeyOrEnd | Step::Key, _) => {
+                    let key = self.token(Self::parse_key)?;
+                    if let Some(PathSegment::Key(k)) = self.path.last_mut() {
+                        *k = key.clone().unwrap_or_default();
+                    }
+                    if let Some(Frame::Object(_, k)) = self.stack.last_mut() {
+                        *k = key;
+                    }
+                    self.step = Step::Value;
+                    continue;
+                }
+                (Step::CommaOrEnd, _) => {
+                    let in_array = matches!(self.stack.last(), Some(Frame::Array(_)));
+                    let close = if in_array { b']' } else { b'}' };
+                    if c == close {
+                        self.pos += 1;
+                        self.end_container()?
+                    } else if c == b',' {
+                        self.pos += 1;
+                        // `path` is empty unless it is tracked.
+                        if let Some(PathSegment::Index(i)) = self.path.last_mut() {
+                            *i += 1;
+                        }
+                        self.step = match (in_array, self.allow_trailing_commas()) {
+                            (true, true) => Step::ElementOrEnd,
+                            (true, false) => Step::Value,
+                            (false, true) => Step::KeyOrEnd,
+                            (false, false) => Step::Key,
+                        };
+                        continue;
+                    } else {
+                        return Err(ParseError::ExpectedCommaOrEnd);
+                    }
+                }
+            };
+
+            // Add the finished value to its parent.
+            match self.stack.last_mut() {
+                None => {
+                    self.step = Step::Value;
+                    return Ok(value);
+                }
+                Some(Frame::Array(array)) => self.builder.push(array, value)?,
+                Some(Frame::Object(obj, key)) => {
+                    if let Some(key) = key.take() {
+                        self.builder.insert(obj, key, value)?;
+                    }
+                }
+            }
+            self.step = Step::CommaOrEnd;
+        }
+    }
+
+    /// Starts the value at the current position, whose first byte is `c`.
+    /// Returns it if it is complete, or `None` for an array or object, which is
+    /// pushed on the stack.
+    fn begin_value(&mut self, c: u8) -> ParseResult<Option<B::Value>> {
+        self.count_nodes(1)?;
+        match c {
+            b'[' => {
+                self.check_depth(self.stack.len() + 1)?;
+                if let Some(values) = self.scan_number_array()? {
+                    let len = match &values {
+                        NumberArray::Float64(v) => v.len(),
+                        NumberArray::BigInt64(v) => v.len(),
+                    };
+                    self.count_nodes(len)?;
+                    return self.builder.number_array(values).map(Some);
+                }
+                self.pos += 1;
+                let array = self.builder.begin_array()?;
+                self.stack.push(Frame::Array(array));
+                if self.compiled.track_path {
+                    self.path.push(PathSegment::Index(0));
+                }
+                self.step = Step::ElementOrEnd;
+                Ok(None)
+            }
+            b'{' => {
+                self.check_depth(self.stack.len() + 1)?;
+                self.pos += 1;
+                let obj = self.builder.begin_object()?;
+                self.stack.push(Frame::Object(obj, None));
+                if self.compiled.track_path {
+                    self.path.push(PathSegment::Key(U16String::new()));
+                }
+                self.step = Step::KeyOrEnd;
+                Ok(None)
+            }
+            _ => {
+                let value = self.token(Self::parse_scalar);
+                // A scalar cut off by the end of pushed input is counted when it is parsed again.
+                if value.is_err() {
+                    self.nodes -= 1;
+                }
+                value.map(Some)
+            }
         }
-        Ok(value)
     }
 
-    fn parse_value(&mut self) -> Result<JsUnknown> {
-        self.skip_whitespace();
-        match self.chars.peek() {
-            Some('n') => self.parse_null(),
-            Some('t') => self.parse_true(),
-            Some('f') => self.parse_false(),
-            Some('"') => self.parse_string().map(|s| s.into_unknown()),
-            Some('[') => self.parse_array(),
-            Some('{') => self.parse_object(),
-            Some(c) if c.is_ascii_digit() || *c == '-' => self.parse_number(),
-            Some(c) => Err(ParseError::UnexpectedCharacter(*c).into()),
-            None => Err(ParseError::UnexpectedEndOfInput.into()),
+    /// Closes the innermost array or object, after its closing bracket.
+    fn end_container(&mut self) -> ParseResult<B::Value> {
+        self.path.pop();
+        match self.stack.pop() {
+            Some(Frame::Array(array)) => self.builder.end_array(array),
+            Some(Frame::Object(obj, _)) => self.builder.end_object(obj),
+            None => unreachable!(),
         }
     }
 
-    fn parse_null(&mut self) -> Result<JsUnknown> {
+    /// The error for input that ends before the document. While more input can
+    /// be pushed, it only suspends parsing.
+    fn end_of_input(&mut self) -> ParseError {
+        self.suspended = self.more;
+        ParseError::UnexpectedEndOfInput
+    }
+
+    /// Called after a comma. With `allow_trailing_commas`, consumes the closing
+    /// bracket if it follows and returns whether it did.
+    fn skip_trailing_comma(&mut self, close: u8) -> ParseResult<bool> {
+        if !self.allow_trailing_commas() {
+            return Ok(false);
+        }
+        self.skip_whitespace()?;
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn check_depth(&self, depth: usize) -> ParseResult<()> {
+        match self.opts.max_depth {
+            Some(max) if depth + self.depth_offset > max as usize => {
+                Err(ParseError::DepthLimitExceeded)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn count_nodes(&mut self, n: usize) -> ParseResult<()> {
+        self.nodes += n;
+        match self.opts.max_nodes {
+            Some(max) if self.nodes > max as usize => Err(ParseError::NodeLimitExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_scalar(&mut self) -> ParseResult<B::Value> {
+        match self.peek() {
+            Some(b'n') => self.parse_null(),
+            Some(b't') => self.parse_true(),
+            Some(b'f') => self.parse_false(),
+            Some(b'"') => {
+                if let Some(value) = self.parse_ascii_string()? {
+                    return Ok(value);
+                }
+                let s = self.parse_string()?;
+                self.string_value(s)
+            }
+            Some(b'\'') if self.allow_single_quotes() => {
+                let s = self.parse_string()?;
+                self.string_value(s)
+            }
+            Some(b'0'..=b'9' | b'-') => self.parse_number(),
+            Some(b'+' | b'.') if self.json5() => self.parse_number(),
+            Some(b'N') if self.allow_non_finite() => {
+                self.expect_str("NaN")?;
+                self.builder.number(Number::Float(f64::NAN))
+            }
+            Some(b'I') if self.allow_non_finite() => {
+                self.expect_str("Infinity")?;
+                self.builder.number(Number::Float(f64::INFINITY))
+            }
+            Some(_) => Err(self.unexpected_character()),
+            None => Err(ParseError::UnexpectedEndOfInput),
+        }
+    }
+
+    /// Passes a string to the builder, as a date if `parse_dates` selects it.
+    fn string_value(&mut self, s: U16String) -> ParseResult<B::Value> {
+        if let Some(Ok(pattern)) = &self.compiled.date_pattern
+            && self
+                .compiled
+                .date_paths
+                .as_ref()
+                .is_none_or(|paths| paths.iter().any(|p| p.matches(&self.path)))
+            // A lone surrogate can't be in a date, so it is fine to replace it.
+            && pattern.is_match(&s.to_string_lossy())
+        {
+            return self.builder.date(s);
+        }
+        self.builder.string(s)
+    }
+
+    /// Whether `strict` turns off `json5` and the `allow_*` options.
+    fn strict(&self) -> bool {
+        self.opts.strict.is_some_and(|e| e)
+    }
+
+    fn json5(&self) -> bool {
+        self.opts.json5.is_some_and(|e| e) && !self.strict()
+    }
+
+    fn allow_non_finite(&self) -> bool {
+        self.opts.allow_non_finite.is_some_and(|e| e) && !self.strict() || self.json5()
+    }
+
+    fn allow_comments(&self) -> bool {
+        self.opts.allow_comments.is_some_and(|e| e) && !self.strict() || self.json5()
+    }
+
+    fn allow_single_quotes(&self) -> bool {
+        self.opts.allow_single_quotes.is_some_and(|e| e) && !self.strict() || self.json5()
+    }
+
+    fn allow_control_characters(&self) -> bool {
+        self.opts.allow_control_characters.is_some_and(|e| e) && !self.strict()
+    }
+
+    fn allow_unicode_whitespace(&self) -> bool {
+        self.opts.allow_unicode_whitespace.is_some_and(|e| e) && !self.strict() || self.json5()
+    }
+
+    fn allow_trailing_commas(&self) -> bool {
+        self.opts.allow_trailing_commas.is_some_and(|e| e) && !self.strict() || self.json5()
+    }
+
+    fn allow_radix_literals(&self) -> bool {
+        self.opts.allow_radix_literals.is_some_and(|e| e) && !self.strict()
+    }
+
+    /// The text of the number from `start` to the current position.
+    fn number_text(&self, start: usize) -> &str {
+        // SAFETY: numbers are ASCII.
+        unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) }
+    }
+
+    /// Offers the number text from `start` to the builder.
+    fn raw_number(&mut self, start: usize) -> ParseResult<Option<B::Value>> {
+        if !self.builder.wants_raw_numbers() {
+            return Ok(None);
+        }
+        // SAFETY: numbers are ASCII.
+        let raw = unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) };
+        self.builder.raw_number(raw, &self.path)
+    }
+
+    fn numbers_as_strings(&self) -> bool {
+        self.opts.numbers_as_strings.is_some_and(|e| e)
+    }
+
+    /// An integer that is not returned as a `number`.
+    fn big_integer(&mut self, bigint: BigInt) -> ParseResult<B::Value> {
+        if self.opts.big_int_as_string.is_some_and(|e| e) {
+            return self
+                .builder
+                .string(U16String::from_str(&bigint.to_string()));
+        }
+        let n = if self.opts.use_native_big_int.is_some_and(|e| e) {
+            Number::BigInt(bigint)
+        } else {
+            Number::BigNumber(BigDecimal::from(bigint))
+        };
+        self.builder.number(n)
+    }
+
+    /// The type `number_types` gives the number at the current path.
+    fn number_type(&self) -> Option<NumberType> {
+        let types = self.compiled.number_types.as_ref()?;
+        types
+            .iter()
+            .find(|(p, _)| p.matches(&self.path))
+            .map(|(_, t)| *t)
+    }
+
+    /// Converts the decimal number `num_str` from `start` to `ty`.
+    fn typed_number(
+        &mut self,
+        start: usize,
+        num_str: &str,
+        ty: NumberType,
+    ) -> ParseResult<B::Value> {
+        let n = match ty {
+            NumberType::String => return self.builder.string(U16String::from_str(num_str)),
+            NumberType::Number => match num_str.parse::<f64>() {
+                Ok(v) if v.is_finite() => Number::Float(v),
+                _ => return self.float_overflow(start, num_str),
+            },
+            NumberType::Int => match num_str.parse::<f64>() {
+                Ok(v) if v.is_finite() && v.fract() == 0.0 => Number::Float(v),
+                _ => return Err(ParseError::InvalidNumber),
+            },
+            NumberType::Bigint => {
+                let big_dec =
+                    BigDecimal::from_str(num_str).map_err(|_| ParseError::InvalidNumber)?;
+                if !big_dec.is_integer() {
+                    return Err(ParseError::InvalidNumber);
+                }
+                let (bigint, _) = big_dec.with_scale(0).into_bigint_and_exponent();
+                Number::BigInt(bigint)
+            }
+            NumberType::Decimal => {
+                let big_dec =
+                    BigDecimal::from_str(num_str).map_err(|_| ParseError::InvalidNumber)?;
+                Number::BigNumber(big_dec)
+            }
+        };
+        self.builder.number(n)
+    }
+
+    /// Applies `float_overflow` to the decimal number `num_str` from `start`,
+    /// which is too large for an `f64`.
+    fn float_overflow(&mut self, start: usize, num_str: &str) -> ParseResult<B::Value> {
+        match self.opts.float_overflow.unwrap_or(FloatOverflow::Null) {
+            FloatOverflow::Null => self.builder.null(),
+            FloatOverflow::Error => {
+                self.pos = start;
+                Err(ParseError::NumberOverflow)
+            }
+            FloatOverflow::Infinity => {
+                let v = if num_str.starts_with('-') {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                };
+                self.builder.number(Number::Float(v))
+            }
+            FloatOverflow::BigNumber => {
+                let big_dec =
+                    BigDecimal::from_str(num_str).map_err(|_| ParseError::InvalidNumber)?;
+                self.builder.number(Number::BigNumber(big_dec))
+            }
+        }
+    }
+
+    /// Applies `precision_loss` to the number from `start` to the current
+    /// position, which became the `number` `v`.
+    fn check_precision(&mut self, start: usize, v: f64) -> ParseResult<()> {
+        // SAFETY: numbers are ASCII.
+        let num_str = unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) };
+        let action = self.opts.precision_loss.unwrap_or(PrecisionLoss::Ignore);
+        if action == PrecisionLoss::Ignore || round_trips(num_str, v) {
+            return Ok(());
+        }
+        match action {
+            PrecisionLoss::Error => {
+                self.pos = start;
+                Err(ParseError::PrecisionLoss)
+            }
+            _ => self.builder.precision_loss(num_str, &self.path),
+        }
+    }
+
+    /// The number options that apply at the current path.
+    fn precision(&self) -> Precision {
+        let selected = self
+            .compiled
+            .big_number_paths
+            .as_ref()
+            .map(|paths| paths.iter().any(|p| p.matches(&self.path)));
+        let plain = selected == Some(false);
+        Precision {
+            always_big: selected.unwrap_or(self.opts.always_parse_as_big.is_some_and(|e| e)),
+            float_big: selected.unwrap_or(self.opts.parse_float_as_big.is_some_and(|e| e)),
+            store_as_string: !plain && self.opts.store_as_string.is_some_and(|e| e),
+            plain,
+        }
+    }
+
+    fn parse_null(&mut self) -> ParseResult<B::Value> {
         self.expect_str("null")?;
-        let null = self.env.get_null()?;
-        Ok(null.into_unknown())
+        self.builder.null()
     }
 
-    fn parse_true(&mut self) -> Result<JsUnknown> {
+    fn parse_true(&mut self) -> ParseResult<B::Value> {
         self.expect_str("true")?;
-        let t = self.env.get_boolean(true)?;
-        Ok(t.into_unknown())
+        self.builder.boolean(true)
     }
 
-    fn parse_false(&mut self) -> Result<JsUnknown> {
+    fn parse_false(&mut self) -> ParseResult<B::Value> {
         self.expect_str("false")?;
-        let t = self.env.get_boolean(false)?;
-        Ok(t.into_unknown())
+        self.builder.boolean(false)
     }
 
-    fn parse_number(&mut self) -> Result<JsUnknown> {
-        let mut num_str = String::with_capacity(64);
+    fn parse_number(&mut self) -> ParseResult<B::Value> {
+        let raw_start = self.pos;
+        let json5 = self.json5();
+        if json5 && self.peek() == Some(b'+') {
+            self.pos += 1;
+            if self.peek() == Some(b'-') {
+                return Err(self.unexpected_character());
+            }
+        }
+
+        let start = self.pos;
         let mut has_decimal = false;
         let mut has_exponent = false;
 
-        if let Some('-') = self.chars.peek() {
-            num_str.push(self.chars.next().unwrap());
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            Some(b'I') if self.allow_non_finite() => {
+                self.expect_str("Infinity")?;
+                if let Some(value) = self.raw_number(raw_start)? {
+                    return Ok(value);
+                }
+                let v = if negative {
+                    f64::NEG_INFINITY
+                } else {
+                    f64::INFINITY
+                };
+                return self.builder.number(Number::Float(v));
+            }
+            Some(b'N') if json5 => {
+                self.expect_str("NaN")?;
+                if let Some(value) = self.raw_number(raw_start)? {
+                    return Ok(value);
+                }
+                return self.builder.number(Number::Float(f64::NAN));
+            }
+            Some(b'0') => {
+                if let Some(radix) = self.radix_prefix() {
+                    self.pos += 2;
+                    return self.parse_radix_integer(raw_start, negative, radix);
+                }
+            }
+            _ => {}
         }
 
-        while let Some(c) = self.chars.peek() {
+        while let Some(c) = self.peek() {
             match c {
-                '0'..='9' => {
-                    num_str.push(self.chars.next().unwrap());
+                b'0'..=b'9' => {
+                    self.pos += 1;
                 }
-                '.' if !has_decimal && !has_exponent => {
-                    num_str.push(self.chars.next().unwrap());
+                b'.' if !has_decimal && !has_exponent => {
+                    self.pos += 1;
                     has_decimal = true;
                 }
-                'e' | 'E' if !has_exponent => {
-                    num_str.push(self.chars.next().unwrap());
+                b'e' | b'E' if !has_exponent => {
+                    self.pos += 1;
                     has_exponent = true;
 
-                    if let Some('+' | '-') = self.chars.peek() {
-                        num_str.push(self.chars.next().unwrap());
+                    if let Some(b'+' | b'-') = self.peek() {
+                        self.pos += 1;
                     }
                 }
                 _ => break,
             }
         }
 
-        if has_decimal || has_exponent {
-            if self.opts.parse_float_as_big.is_some_and(|e| e) {
-                let big_dec = BigDecimal::from_str(&num_str)
-                    .map_err(|e| Error::new(Status::InvalidArg, e))?;
-                let n = BigNumber(big_dec);
-                let napi_value = unsafe { BigNumber::to_napi_value(self.env.raw(), n)? };
-                unsafe { JsUnknown::from_raw(self.env.raw(), napi_value) }
+        if self.cut_off(1) {
+            return Err(ParseError::UnexpectedEndOfInput);
+        }
+        if let Some(i) = invalid_number_at(&self.input[start..self.pos], json5) {
+            self.pos = start + i;
+            return Err(ParseError::InvalidNumber);
+        }
+        if let Some(value) = self.raw_number(raw_start)? {
+            return Ok(value);
+        }
+        // SAFETY: only ASCII bytes were consumed above.
+        let num_str = unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) };
+        if let Some(ty) = self.number_type() {
+            return self.typed_number(start, &num_str.to_owned(), ty);
+        }
+        if self.numbers_as_strings() {
+            let raw = self.number_text(raw_start);
+            return self.builder.string(U16String::from_str(raw));
+        }
+        // JSON5 forms like `.5` are not kept, so the text is always valid JSON.
+        if self.opts.lossless_numbers.is_some_and(|e| e)
+            && invalid_number_at(num_str.as_bytes(), false).is_none()
+        {
+            return self.builder.number(Number::Lossless(num_str.to_owned()));
+        }
+        let precision = self.precision();
+
+        let n = if has_decimal || has_exponent {
+            if self.opts.parse_float_as_string.is_some_and(|e| e) {
+                return self.builder.string(U16String::from_str(num_str));
+            } else if precision.float_big {
+                if precision.store_as_string {
+                    return self.builder.string(U16String::from_str(num_str));
+                }
+                let big_dec =
+                    BigDecimal::from_str(num_str).map_err(|_| ParseError::InvalidNumber)?;
+                Number::BigNumber(big_dec)
             } else if let Ok(v) = num_str.parse::<f64>()
                 && v.is_finite()
+                && (!precision.store_as_string || round_trips(num_str, v))
             {
-                let napi_value = self.env.create_double(v)?;
-                Ok(napi_value.into_unknown())
+                self.check_precision(start, v)?;
+                Number::Float(v)
+            } else if precision.store_as_string {
+                return self.builder.string(U16String::from_str(num_str));
             } else {
-                Ok(self.env.get_null()?.into_unknown())
+                return self.float_overflow(start, &num_str.to_owned());
             }
         } else {
-            if !self.opts.always_parse_as_big.is_some_and(|e| e)
-                && let Ok(v) = num_str.as_str().parse::<i64>()
+            if !precision.always_big
+                && let Ok(v) = num_str.parse::<i64>()
+                && (precision.plain || (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v))
             {
-                const MAX: i64 = 9_007_199_254_740_991;
-                if (-MAX..=MAX).contains(&v) {
-                    return Ok(self.env.create_int64(v)?.into_unknown());
+                if !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v) {
+                    self.check_precision(start, v as f64)?;
                 }
+                return self.builder.number(Number::Int(v));
             }
+            if precision.plain {
+                return match num_str.parse::<f64>() {
+                    Ok(v) if v.is_finite() => {
+                        self.check_precision(start, v)?;
+                        self.builder.number(Number::Float(v))
+                    }
+                    _ => self.float_overflow(start, &num_str.to_owned()),
+                };
+            }
+            if precision.store_as_string {
+                return self.builder.string(U16String::from_str(num_str));
+            }
+
+            let bigint =
+                BigInt::from_str_radix(num_str, 10).map_err(|_| ParseError::InvalidNumber)?;
+            return self.big_integer(bigint);
+        };
+        self.builder.number(n)
+    }
+
+    /// Whether fewer than `n` bytes are left in the chunk and the input goes on
+    /// in the next one or may be pushed, so a token that ends here may really
+    /// be cut off.
+    fn cut_off(&self, n: usize) -> bool {
+        self.input.len() - self.pos < n && (!self.rest.is_empty() || self.more)
+    }
+
+    /// The radix of a `0x`, `0b` or `0o` prefix at the current position, if the
+    /// options allow it.
+    fn radix_prefix(&self) -> Option<u32> {
+        let allow_radix = self.allow_radix_literals();
+        match self.input.get(self.pos + 1)? {
+            b'x' | b'X' if allow_radix || self.json5() => Some(16),
+            b'b' | b'B' if allow_radix => Some(2),
+            b'o' | b'O' if allow_radix => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Parses the digits of a `0x`-style integer literal, after the prefix.
+    fn parse_radix_integer(
+        &mut self,
+        raw_start: usize,
+        negative: bool,
+        radix: u32,
+    ) -> ParseResult<B::Value> {
+        let start = self.pos;
+        while let Some(c) = self.peek()
+            && (c as char).is_digit(radix)
+        {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(ParseError::InvalidNumber);
+        }
+        if self.cut_off(1) {
+            return Err(ParseError::UnexpectedEndOfInput);
+        }
+        if let Some(value) = self.raw_number(raw_start)? {
+            return Ok(value);
+        }
+        if self.numbers_as_strings() && self.number_type().is_none() {
+            let raw = self.number_text(raw_start);
+            return self.builder.string(U16String::from_str(raw));
+        }
+
+        // SAFETY: only ASCII digits were consumed above.
+        let digits = unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) };
+        let mut bigint =
+            BigInt::from_str_radix(digits, radix).map_err(|_| ParseError::InvalidNumber)?;
+        if negative {
+            bigint = -bigint;
+        }
+        if let Some(ty) = self.number_type() {
+            return self.typed_number(raw_start, &bigint.to_string(), ty);
+        }
 
-            let bigint = bigdecimal::num_bigint::BigInt::from_str_radix(&num_str, 10)
-                .map_err(|e| Error::new(Status::InvalidArg, e))?;
-            let (sign, int) = bigint.into_parts();
-            let sign_bit = sign == Sign::Minus;
-            let words = int.to_u64_digits();
-            self.env
-                .create_bigint_from_words(sign_bit, words)?
-                .into_unknown()
+        let precision = self.precision();
+        if !precision.always_big
+            && let Some(v) = bigint.to_i64()
+            && (precision.plain || (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v))
+        {
+            return self.builder.number(Number::Int(v));
+        }
+        if precision.plain {
+            return match bigint.to_f64() {
+                Some(v) if v.is_finite() => self.builder.number(Number::Float(v)),
+                _ => self.float_overflow(raw_start, &bigint.to_string()),
+            };
         }
+        if precision.store_as_string {
+            return self
+                .builder
+                .string(U16String::from_str(&bigint.to_string()));
+        }
+        self.big_integer(bigint)
     }
 
-    fn parse_string(&mut self) -> Result<JsString> {
-        self.expect_char('"')?;
-        let mut s = Utf16String::new();
+    fn parse_string(&mut self) -> ParseResult<U16String> {
+        let json5 = self.json5();
+        let single_quotes = self.allow_single_quotes();
+        let quote = match self.peek() {
+            Some(b'\'') if single_quotes => b'\'',
+            _ => b'"',
+        };
+        self.expect_byte(quote)?;
+        let mut s = U16String::new();
+        let control_chars = self.allow_control_characters();
 
         loop {
-            match self.chars.next() {
-                Some('"') => {
+            // Copy the run of unescaped bytes in one go.
+            let start = self.pos;
+            while let Some(c) = self.peek()
+                && c != quote
+                && c != b'\\'
+                && (control_chars || c >= 0x20)
+            {
+                self.pos += 1;
+            }
+            if self.pos > start {
+                let run = std::str::from_utf8(&self.input[start..self.pos])
+                    .map_err(|_| ParseError::InvalidUtf8)?;
+                s.push_str(run);
+                self.check_string_length(&s)?;
+            }
+
+            match self.next() {
+                Some(c) if c == quote => {
                     break;
                 }
-                Some('\\') => match self.chars.next() {
-                    Some('"') => s.push('"'),
-                    Some('\\') => s.push('\\'),
-                    Some('/') => s.push('/'),
-                    Some('b') => s.push('\u{0008}'),
-                    Some('f') => s.push('\u{000C}'),
-                    Some('n') => s.push('\n'),
-                    Some('r') => s.push('\r'),
-                    Some('t') => s.push('\t'),
-                    Some('u') => {
-                        let hex_str = self.read_n_chars(4)?;
-                        let code = u32::from_str_radix(&hex_str, 16)
-                            .map_err(|_| ParseError::InvalidEscapeSequence('u'))?;
-                        s.push(
-                            std::char::from_u32(code)
-                                .ok_or(ParseError::InvalidEscapeSequence('u'))?,
-                        );
+                Some(b'\\') => match self.next() {
+                    Some(b'"') => s.push_char('"'),
+                    Some(b'\\') => s.push_char('\\'),
+                    Some(b'/') => s.push_char('/'),
+                    Some(b'b') => s.push_char('\u{0008}'),
+                    Some(b'f') => s.push_char('\u{000C}'),
+                    Some(b'n') => s.push_char('\n'),
+                    Some(b'r') => s.push_char('\r'),
+                    Some(b't') => s.push_char('\t'),
+                    Some(b'u') => self.parse_unicode_escape(&mut s)?,
+                    Some(b'\'') if single_quotes => s.push_char('\''),
+                    Some(b'v') if json5 => s.push_char('\u{000B}'),
+                    Some(b'0') if json5 && !matches!(self.peek(), Some(b'0'..=b'9')) => {
+                        s.push_char('\0')
                     }
-                    Some(c) => return Err(ParseError::InvalidEscapeSequence(c).into()),
-                    None => return Err(ParseError::UnexpectedEndOfInput.into()),
+                    Some(b'x') if json5 => {
+                        let code = self.read_hex(2, 'x')?;
+                        s.push_char(char::from(code as u8));
+                    }
+                    // Line continuations.
+                    Some(b'\n') if json5 => {}
+                    Some(b'\r') if json5 => {
+                        if self.peek() == Some(b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    // Any other character except digits escapes to itself.
+                    Some(c) if json5 && !c.is_ascii_digit() => {
+                        self.pos -= 1;
+                        let c = self.current_char()?;
+                        self.pos += c.len_utf8();
+                        if c != '\u{2028}' && c != '\u{2029}' {
+                            s.push_char(c);
+                        }
+                    }
+                    Some(_) => {
+                        self.pos -= 1;
+                        let c = self.current_char()?;
+                        return Err(ParseError::InvalidEscapeSequence(c));
+                    }
+                    None => return Err(ParseError::UnexpectedEndOfInput),
                 },
+                // An unescaped control character.
                 Some(c) => {
-                    s.push(c);
+                    self.pos -= 1;
+                    return Err(ParseError::UnexpectedCharacter(c as char));
                 }
-                None => return Err(ParseError::UnexpectedEndOfInput.into()),
+                None => return Err(ParseError::UnexpectedEndOfInput),
+            }
+            self.check_string_length(&s)?;
+        }
+        Ok(s)
+    }
+
+    /// Parses the hex digits of a `\u` escape. A high surrogate followed by a
+    /// `\u` escaped low surrogate is combined into one char, unpaired surrogates
+    /// are handled according to `lone_surrogates`.
+    fn parse_unicode_escape(&mut self, s: &mut U16String) -> ParseResult<()> {
+        let code = self.read_hex(4, 'u')?;
+        if (0xD800..=0xDBFF).contains(&code) && self.cut_off(2) {
+            return Err(ParseError::UnexpectedEndOfInput);
+        }
+        if (0xD800..=0xDBFF).contains(&code) && self.input[self.pos..].starts_with(b"\\u") {
+            let start = self.pos;
+            self.pos += 2;
+            let low = self.read_hex(4, 'u')?;
+            if (0xDC00..=0xDFFF).contains(&low) {
+                let c = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                s.push_char(char::from_u32(c).ok_or(ParseError::InvalidEscapeSequence('u'))?);
+                return Ok(());
             }
+            self.pos = start;
+        }
+
+        if let Some(c) = char::from_u32(code) {
+            s.push_char(c);
+            return Ok(());
+        }
+        match self.opts.lone_surrogates.unwrap_or(LoneSurrogates::Error) {
+            LoneSurrogates::Error => return Err(ParseError::InvalidEscapeSequence('u')),
+            LoneSurrogates::Replace => s.push_char(char::REPLACEMENT_CHARACTER),
+            // JS strings can hold unpaired surrogates, and so can a `U16String`.
+            LoneSurrogates::Preserve => s.push_slice([code as u16]),
         }
-        self.env.create_string_utf16(s.as_slice())
+        Ok(())
     }
 
-    fn parse_array(&mut self) -> Result<JsUnknown> {
-        self.expect_char('[')?;
-        self.skip_whitespace();
+    /// Fast path for a plain printable ASCII string, or `None` for any other string.
+    fn parse_ascii_string(&mut self) -> ParseResult<Option<B::Value>> {
+        if self.compiled.date_pattern.is_some() {
+            return Ok(None);
+        }
+        let start = self.pos + 1;
+        let Some(len) = self.input[start..]
+            .iter()
+            .position(|&c| c == b'"' || c == b'\\' || !(0x20..0x80).contains(&c))
+        else {
+            return Ok(None);
+        };
+        if self.input[start + len] != b'"' {
+            return Ok(None);
+        }
+        if let Some(max) = self.opts.max_string_length
+            && len > max as usize
+        {
+            return Err(ParseError::StringTooLong);
+        }
 
-        let mut array = self.env.create_array(0)?;
+        self.pos = start + len + 1;
+        self.builder
+            .ascii_string(&self.input[start..start + len])
+            .map(Some)
+    }
 
-        if let Some(']') = self.chars.peek() {
-            self.chars.next();
-            let obj = array.coerce_to_object()?;
-            return Ok(obj.into_unknown());
+    /// Checks `max_string_length`, counted in UTF-16 code units like `String.length`.
+    fn check_string_length(&self, s: &U16String) -> ParseResult<()> {
+        match self.opts.max_string_length {
+            Some(max) if s.len() > max as usize => Err(ParseError::StringTooLong),
+            _ => Ok(()),
         }
+    }
 
-        let mut index = 0;
-        loop {
-            let value = self.parse_value()?;
-            array.set(index, value)?;
-            index += 1;
-            self.skip_whitespace();
-
-            match self.chars.peek() {
-                Some(',') => {
-                    self.chars.next();
-                    self.skip_whitespace();
-                }
-                Some(']') => {
-                    self.chars.next();
+    /// Parses an object key and the colon after it. Returns `None` if the
+    /// property should be left out of the object.
+    fn parse_key(&mut self) -> ParseResult<Option<U16String>> {
+        let mut key = match self.peek() {
+            Some(b'"' | b'\'') => self.parse_string()?,
+            _ if self.json5() => self.parse_identifier()?,
+            _ => self.parse_string()?,
+        };
+        if let Some(case) = self.opts.key_transform {
+            key = U16String::from_vec(case.convert(key.as_slice()));
+        }
+        self.skip_whitespace()?;
+        match self.next() {
+            Some(b':') => {}
+            Some(_) => {
+                self.pos -= 1;
+                return Err(ParseError::ExpectedColon);
+            }
+            None => return Err(ParseError::UnexpectedEndOfInput),
+        }
+
+        let action = if key.as_ustr() == PROTO_KEY {
+            // A `Map` entry can't reach the prototype, so only reject it on request.
+            let default = if self.opts.objects_as_maps.is_some_and(|e| e) {
+                KeyAction::Preserve
+            } else {
+                KeyAction::Error
+            };
+            self.opts.proto_action.unwrap_or(default)
+        } else if key.as_ustr() == CONSTRUCTOR_KEY || key.as_ustr() == PROTOTYPE_KEY {
+            self.opts.constructor_action.unwrap_or(KeyAction::Preserve)
+        } else {
+            KeyAction::Preserve
+        };
+
+        match action {
+            KeyAction::Error => Err(ParseError::ForbiddenKey),
+            KeyAction::Ignore => Ok(None),
+            KeyAction::Preserve => {
+                self.builder.key(&key)?;
+                Ok(Some(key))
+            }
+        }
+    }
+
+    /// Parses an unquoted JSON5 object key.
+    fn parse_identifier(&mut self) -> ParseResult<U16String> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == b'_' || c == b'$' {
+                self.pos += 1;
+            } else if !c.is_ascii() {
+                let c = self.current_char()?;
+                if !c.is_alphanumeric() {
                     break;
                 }
-                Some(c) => return Err(ParseError::UnexpectedCharacter(*c).into()),
-                None => return Err(ParseError::UnexpectedEndOfInput.into()),
+                self.pos += c.len_utf8();
+            } else {
+                break;
             }
         }
 
-        let obj = array.coerce_to_object()?;
-        Ok(obj.into_unknown())
+        if self.cut_off(1) {
+            return Err(ParseError::UnexpectedEndOfInput);
+        }
+        if self.pos == start || self.input[start].is_ascii_digit() {
+            self.pos = start;
+            return Err(self.unexpected_character());
+        }
+        // SAFETY: the bytes were checked to be ASCII or decoded as chars above.
+        let name = unsafe { std::str::from_utf8_unchecked(&self.input[start..self.pos]) };
+        Ok(U16String::from_str(name))
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
     }
 
-    fn parse_object(&mut self) -> Result<JsUnknown> {
-        self.expect_char('{')?;
-        self.skip_whitespace();
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
 
-        let mut obj = self.env.create_object()?;
+    /// Decodes the character at the current position without consuming it.
+    fn current_char(&self) -> ParseResult<char> {
+        let chunk = self.input[self.pos..]
+            .utf8_chunks()
+            .next()
+            .ok_or(ParseError::UnexpectedEndOfInput)?;
+        chunk.valid().chars().next().ok_or(ParseError::InvalidUtf8)
+    }
 
-        if let Some('}') = self.chars.peek() {
-            self.chars.next();
-            return Ok(obj.into_unknown());
+    fn unexpected_character(&self) -> ParseError {
+        match self.current_char() {
+            Ok(c) => ParseError::UnexpectedCharacter(c),
+            Err(e) => e,
         }
+    }
 
+    /// Skips whitespace and comments. Between tokens this moves on to the next
+    /// chunk, so the input only runs out at the end of the last one.
+    fn skip_whitespace(&mut self) -> ParseResult<()> {
         loop {
-            let key = self.parse_string()?;
+            self.token(Self::skip_spaces)?;
+            if self.pos == self.input.len() && !self.in_token && self.next_chunk() {
+                continue;
+            }
+            if !self.allow_comments() || self.peek() != Some(b'/') {
+                return Ok(());
+            }
+            self.token(Self::skip_comment)?;
+        }
+    }
 
-            self.skip_whitespace();
-            self.expect_char(':')?;
-            self.skip_whitespace();
+    /// Runs `f` to parse a token from the current position. If it fails near
+    /// the end of the chunk, the token may be cut off, so it is parsed again
+    /// once enough of the next chunks are appended for it to end. Errors from
+    /// the builder are not retried since the builder may already have been called.
+    ///
+    /// With pushed input, a token cut off by its end is left for the next push
+    /// instead, and parsing is suspended.
+    fn token<T>(&mut self, f: impl Fn(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        if self.in_token || (self.rest.is_empty() && !self.more) {
+            return f(self);
+        }
+        self.in_token = true;
+        let mut start = self.pos;
+        let mut scan = TokenScan::default();
+        loop {
+            let result = f(self);
+            // A UTF-8 character is at most 4 bytes.
+            if let Err(e) = &result
+                && !matches!(e, ParseError::NapiError(_))
+                && self.pos + 4 > self.input.len()
+            {
+                if !self.rest.is_empty() {
+                    self.extend_input(start);
+                    start = 0;
+                    while !scan.may_end(&self.input) && !self.rest.is_empty() {
+                        self.extend_input(0);
+                    }
+                    continue;
+                }
+                if matches!(
+                    e,
+                    ParseError::UnexpectedEndOfInput | ParseError::InvalidUtf8
+                ) {
+                    self.pos = start;
+                    self.suspended = true;
+                }
+            }
+            self.in_token = false;
+            return result;
+        }
+    }
 
-            let value = self.parse_value()?;
+    /// Moves on to the next chunk once `input` has been consumed.
+    fn next_chunk(&mut self) -> bool {
+        let Some((&chunk, rest)) = self.rest.split_first() else {
+            return false;
+        };
+        self.consume(self.input.len());
+        self.input = Cow::Borrowed(chunk);
+        self.rest = rest;
+        self.pos = 0;
+        true
+    }
 
-            obj.set_property(key, value)?;
+    /// Drops `input` before `start` and appends the next chunk to it. `input`
+    /// is copied once, and the copy is then grown in place.
+    fn extend_input(&mut self, start: usize) {
+        let Some((&chunk, rest)) = self.rest.split_first() else {
+            return;
+        };
+        self.consume(start);
+        if let Cow::Owned(buf) = &mut self.input {
+            buf.drain(..start);
+            buf.extend_from_slice(chunk);
+        } else {
+            let mut buf = Vec::with_capacity(self.input.len() - start + chunk.len());
+            buf.extend_from_slice(&self.input[start..]);
+            buf.extend_from_slice(chunk);
+            self.input = Cow::Owned(buf);
+        }
+        self.rest = rest;
+        self.pos = 0;
+    }
 
-            self.skip_whitespace();
+    /// Counts the first `n` bytes of `input` as left behind, for error positions.
+    fn consume(&mut self, n: usize) {
+        let consumed = &self.input[..n];
+        // Count UTF-8 lead bytes, i.e. characters.
+        let chars = |bytes: &[u8]| bytes.iter().filter(|&&c| (c & 0xC0) != 0x80).count();
+        match consumed.iter().rposition(|&c| c == b'\n') {
+            Some(i) => {
+                self.line_base += consumed.iter().filter(|&&c| c == b'\n').count();
+                self.column_base = chars(&consumed[i + 1..]);
+            }
+            None => self.column_base += chars(consumed),
+        }
+        self.base += n;
+    }
 
-            match self.chars.peek() {
-                Some(',') => {
-                    self.chars.next();
-                    self.skip_whitespace();
+    /// Skips a `// line` or `/* block */` comment starting at the current position.
+    fn skip_comment(&mut self) -> ParseResult<()> {
+        match self.input.get(self.pos + 1) {
+            Some(b'/') => {
+                self.pos += 2;
+                while let Some(c) = self.next() {
+                    if c == b'\n' {
+                        return Ok(());
+                    }
                 }
-                Some('}') => {
-                    self.chars.next();
-                    break;
+                if self.cut_off(1) {
+                    return Err(ParseError::UnexpectedEndOfInput);
                 }
-                Some(c) => return Err(ParseError::UnexpectedCharacter(*c).into()),
-                None => return Err(ParseError::UnexpectedEndOfInput.into()),
+                Ok(())
             }
+            Some(b'*') => {
+                self.pos += 2;
+                loop {
+                    match self.next() {
+                        Some(b'*') if self.peek() == Some(b'/') => {
+                            self.pos += 1;
+                            return Ok(());
+                        }
+                        Some(_) => {}
+                        None => return Err(ParseError::UnexpectedEndOfInput),
+                    }
+                }
+            }
+            None if self.cut_off(2) => Err(ParseError::UnexpectedEndOfInput),
+            _ => Err(self.unexpected_character()),
         }
-
-        Ok(obj.into_unknown())
     }
 
-    fn skip_whitespace(&mut self) {
-        while let Some(c) = self.chars.peek() {
-            if c.is_whitespace() {
-                self.chars.next();
+    fn skip_spaces(&mut self) -> ParseResult<()> {
+        if !self.allow_unicode_whitespace() {
+            while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+                self.pos += 1;
+            }
+            return Ok(());
+        }
+
+        while let Some(c) = self.peek() {
+            if c.is_ascii() {
+                if !(c as char).is_whitespace() {
+                    break;
+                }
+                self.pos += 1;
             } else {
-                break;
+                let c = self.current_char()?;
+                if !c.is_whitespace() && !(c == '\u{FEFF}' && self.json5()) {
+                    break;
+                }
+                self.pos += c.len_utf8();
             }
         }
+        Ok(())
     }
 
-    fn expect_char(&mut self, expected: char) -> std::result::Result<(), ParseError> {
-        match self.chars.next() {
-            Some(c) if c == expected => Ok(()),
-            Some(c) => Err(ParseError::UnexpectedCharacter(c)),
+    fn expect_byte(&mut self, expected: u8) -> ParseResult<()> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected_character()),
             None => Err(ParseError::UnexpectedEndOfInput),
         }
     }
 
-    fn expect_str(&mut self, expected: &str) -> std::result::Result<(), ParseError> {
-        for c in expected.chars() {
-            self.expect_char(c)?;
+    fn expect_str(&mut self, expected: &str) -> ParseResult<()> {
+        for c in expected.bytes() {
+            self.expect_byte(c)?;
         }
         Ok(())
     }
 
-    fn read_n_chars(&mut self, n: usize) -> std::result::Result<String, ParseError> {
-        let mut s = String::with_capacity(n);
-        for _ in 0..n {
-            match self.chars.next() {
-                Some(c) => {
-                    s.push(c);
+    /// Reads the `n` hex digits of a `\u` or `\x` escape.
+    fn read_hex(&mut self, n: usize, escape: char) -> ParseResult<u32> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + n)
+            .ok_or(ParseError::UnexpectedEndOfInput)?;
+        let mut code = 0;
+        for &d in digits {
+            let v = (d as char)
+                .to_digit(16)
+                .ok_or(ParseError::InvalidEscapeSequence(escape))?;
+            code = code * 16 + v;
+        }
+        self.pos += n;
+        Ok(code)
+    }
+}
+
+/// How far a cut off token has been scanned for its end, so that a long string
+/// or comment split over many chunks is not parsed again for every chunk.
+#[derive(Default)]
+struct TokenScan {
+    /// The bytes of the token scanned so far.
+    scanned: usize,
+    /// Whether the last byte scanned is a backslash in a string.
+    escaped: bool,
+    /// Whether the token may end in the bytes scanned.
+    ended: bool,
+}
+
+impl TokenScan {
+    /// Scans what was added to `token` since the last call, and returns whether
+    /// the token may end in it. Only strings and comments are scanned, other
+    /// tokens are short and may end anywhere.
+    fn may_end(&mut self, token: &[u8]) -> bool {
+        if self.ended {
+            return true;
+        }
+        let from = self.scanned;
+        self.scanned = token.len();
+        self.ended = match token {
+            [quote @ (b'"' | b'\''), ..] => {
+                let mut ended = false;
+                for &c in &token[from.max(1)..] {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if c == b'\\' {
+                        self.escaped = true;
+                    } else if c == *quote || c < 0x20 {
+                        // A control character may be an error, which parsing finds.
+                        ended = true;
+                        break;
+                    }
                 }
-                None => return Err(ParseError::UnexpectedEndOfInput),
+                ended
             }
+            // `*/` may be split between the bytes scanned before and the new ones.
+            [b'/', b'*', ..] => token[from.max(3) - 1..].windows(2).any(|w| w == b"*/"),
+            [b'/', b'/', ..] => token[from.max(2)..].contains(&b'\n'),
+            _ => true,
+        };
+        self.ended
+    }
+}
+
+/// Whether no option changes how single numbers are converted, so that
+/// `typed_arrays` can convert a whole array at once.
+fn plain_numbers(opts: &Options) -> bool {
+    let set = |o: Option<bool>| o.is_some_and(|e| e);
+    !set(opts.always_parse_as_big)
+        && !set(opts.big_int_as_string)
+        && !set(opts.parse_float_as_big)
+        && !set(opts.parse_float_as_string)
+        && !set(opts.store_as_string)
+        && !set(opts.lossless_numbers)
+        && !set(opts.numbers_as_strings)
+        && opts.big_number_paths.is_none()
+        && opts.number_types.is_none()
+        && opts.number_handler.is_none()
+        && opts
+            .precision_loss
+            .is_none_or(|p| p == PrecisionLoss::Ignore)
+}
+
+/// Whether `v`, the nearest `f64` to the decimal `num_str`, has the same value.
+///
+/// `f64`'s `Display` prints the shortest digits that read back as `v`, so the
+/// text only loses precision if those digits are a different number.
+fn round_trips(num_str: &str, v: f64) -> bool {
+    match (
+        BigDecimal::from_str(num_str),
+        BigDecimal::from_str(&v.to_string()),
+    ) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Finds the first byte of `bytes` that breaks the RFC 8259 number grammar
+/// `-? (0 | [1-9][0-9]*) (\.[0-9]+)? ([eE][+-]?[0-9]+)?`. JSON5 also allows a
+/// leading or trailing decimal point. Returns `None` for a valid number.
+pub fn invalid_number_at(bytes: &[u8], json5: bool) -> Option<usize> {
+    let digits = |i: usize| bytes[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+
+    let mut i = 0;
+    if bytes.first() == Some(&b'-') {
+        i += 1;
+    }
+
+    let int_digits = match bytes.get(i) {
+        Some(b'0') => 1,
+        Some(b'1'..=b'9') => digits(i),
+        Some(b'.') if json5 => 0,
+        _ => return Some(i),
+    };
+    i += int_digits;
+
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        let n = digits(i);
+        if n == 0 && !(json5 && int_digits > 0) {
+            return Some(i);
         }
-        Ok(s)
+        i += n;
     }
+
+    if let Some(b'e' | b'E') = bytes.get(i) {
+        i += 1;
+        if let Some(b'+' | b'-') = bytes.get(i) {
+            i += 1;
+        }
+        let n = digits(i);
+        if n == 0 {
+            return Some(i);
+        }
+        i += n;
+    }
+
+    (i < bytes.len()).then_some(i)
 }
@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{ArrayBuffer, Either, ObjectFinalize, Uint8Array};
+use napi_ohos::{Env, JsUnknown, Result};
+
+use crate::builder::{JsBuilder, KeyRefs, Reviver};
+use crate::error::{JsonErrorCode, with_code};
+use crate::options::Options;
+use crate::parse::{Compiled, JsonParser};
+
+/// Parses documents with options that are prepared once, for code that parses
+/// many documents with the same options. Paths and `date_pattern` are compiled
+/// by the constructor rather than on every call, and the JS strings of short
+/// keys are kept from one call to the next.
+#[napi(js_name = "JsonParser", custom_finalize)]
+pub struct Parser {
+    opts: Options,
+    compiled: Compiled,
+    keys: KeyRefs,
+}
+
+#[napi]
+impl Parser {
+    #[napi(constructor)]
+    pub fn new(options: Option<Options>) -> Result<Self> {
+        let opts = options.unwrap_or_default();
+        let compiled = Compiled::new(&opts);
+        compiled.check()?;
+        Ok(Parser {
+            opts,
+            compiled,
+            keys: KeyRefs::default(),
+        })
+    }
+
+    #[napi]
+    pub fn parse(
+        &mut self,
+        env: Env,
+        s: String,
+        reviver: Option<Reviver<'_>>,
+    ) -> Result<JsUnknown> {
+        self.parse_bytes(&env, s.as_bytes(), reviver)
+    }
+
+    #[napi]
+    pub fn parse_buffer(
+        &mut self,
+        env: Env,
+        buf: Either<ArrayBuffer, Uint8Array>,
+        reviver: Option<Reviver<'_>>,
+    ) -> Result<JsUnknown> {
+        let bytes: &[u8] = match &buf {
+            Either::A(b) => b,
+            Either::B(b) => b,
+        };
+        self.parse_bytes(&env, bytes, reviver)
+    }
+}
+
+impl Parser {
+    fn parse_bytes(
+        &mut self,
+        env: &Env,
+        bytes: &[u8],
+        reviver: Option<Reviver<'_>>,
+    ) -> Result<JsUnknown> {
+        let builder = JsBuilder::new(env, &self.opts, reviver)
+            .map_err(|e| with_code(env, e, JsonErrorCode::Internal))?
+            .with_key_refs(&mut self.keys);
+        let compiled = Cow::Borrowed(&self.compiled);
+        let mut parser = JsonParser::with_compiled(bytes, &self.opts, compiled, builder);
+        parser.parse().map_err(|e| e.into_error(env))
+    }
+}
+
+impl ObjectFinalize for Parser {
+    fn finalize(mut self, env: Env) -> Result<()> {
+        self.keys.clear(env)
+    }
+}
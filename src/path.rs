@@ -0,0 +1,114 @@
+use widestring::U16String;
+
+/// One step of the path from the root to a value.
+#[derive(Debug, Clone)]
+pub enum PathSegment {
+    Index(u32),
+    Key(U16String),
+}
+
+/// Writes `path` as a JSON pointer, e.g. `/orders/0/price`.
+pub fn to_pointer(path: &[PathSegment]) -> String {
+    let mut pointer = String::new();
+    for segment in path {
+        pointer.push('/');
+        match segment {
+            PathSegment::Index(i) => pointer.push_str(&i.to_string()),
+            PathSegment::Key(key) => {
+                pointer.push_str(&key.to_string_lossy().replace('~', "~0").replace('/', "~1"))
+            }
+        }
+    }
+    pointer
+}
+
+/// Splits a JSON pointer into its unescaped tokens, or `None` if it is not one.
+/// The empty pointer is the root.
+pub fn from_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+/// One step of a path given in the options.
+#[derive(Debug, Clone)]
+pub enum PathPattern {
+    /// `*`, any key or index.
+    Any,
+    /// `[]`, any index.
+    AnyIndex,
+    /// `[0]`, one index.
+    Index(u32),
+    /// A key, or an index written like a key, e.g. `/items/0`.
+    Key(U16String),
+}
+
+impl PathPattern {
+    fn matches(&self, segment: &PathSegment) -> bool {
+        match (self, segment) {
+            (PathPattern::Any, _) => true,
+            (PathPattern::AnyIndex, PathSegment::Index(_)) => true,
+            (PathPattern::Index(a), PathSegment::Index(b)) => a == b,
+            (PathPattern::Key(a), PathSegment::Key(b)) => a == b,
+            (PathPattern::Key(a), PathSegment::Index(b)) => *a == U16String::from(b.to_string()),
+            _ => false,
+        }
+    }
+}
+
+/// A path in the options, either a JSON pointer like `/orders/0/price` or a
+/// dotted path like `orders[].price`. Both accept `*` for any key or index.
+#[derive(Debug, Clone)]
+pub struct Path(Vec<PathPattern>);
+
+impl Path {
+    pub fn parse(s: &str) -> Self {
+        let mut patterns = Vec::new();
+        if let Some(pointer) = s.strip_prefix('/') {
+            for token in pointer.split('/') {
+                let token = token.replace("~1", "/").replace("~0", "~");
+                patterns.push(key_pattern(&token));
+            }
+            return Path(patterns);
+        }
+
+        for part in s.split('.').filter(|p| !p.is_empty()) {
+            let (name, mut rest) = part.split_once('[').unwrap_or((part, ""));
+            if !name.is_empty() {
+                patterns.push(key_pattern(name));
+            }
+            // `[]`, `[0]` or `[*]` after the key, possibly repeated.
+            while !rest.is_empty() {
+                let (inner, after) = rest.split_once(']').unwrap_or((rest, ""));
+                patterns.push(match inner {
+                    "" => PathPattern::AnyIndex,
+                    "*" => PathPattern::Any,
+                    _ => match inner.parse() {
+                        Ok(i) => PathPattern::Index(i),
+                        Err(_) => PathPattern::Key(U16String::from(inner)),
+                    },
+                });
+                rest = after.strip_prefix('[').unwrap_or("");
+            }
+        }
+        Path(patterns)
+    }
+
+    pub fn matches(&self, path: &[PathSegment]) -> bool {
+        self.0.len() == path.len() && self.0.iter().zip(path).all(|(p, s)| p.matches(s))
+    }
+}
+
+fn key_pattern(name: &str) -> PathPattern {
+    if name == "*" {
+        PathPattern::Any
+    } else {
+        PathPattern::Key(U16String::from(name))
+    }
+}
@@ -0,0 +1,63 @@
+use std::ptr;
+
+use napi_ohos::{Env, JsString, JsUnknown, NapiRaw, NapiValue, Result, check_status, sys};
+
+// ArkTS additions to Node-API that napi-ohos has no bindings for.
+unsafe extern "C" {
+    fn napi_create_sendable_array_with_length(
+        env: sys::napi_env,
+        length: usize,
+        result: *mut sys::napi_value,
+    ) -> sys::napi_status;
+
+    fn napi_create_sendable_object_with_properties(
+        env: sys::napi_env,
+        property_count: usize,
+        properties: *const sys::napi_property_descriptor,
+        result: *mut sys::napi_value,
+    ) -> sys::napi_status;
+}
+
+/// Creates a Sendable array holding `elements`, which must all be Sendable.
+pub fn create_array(env: &Env, elements: Vec<JsUnknown>) -> Result<JsUnknown> {
+    let mut array = ptr::null_mut();
+    check_status!(unsafe {
+        napi_create_sendable_array_with_length(env.raw(), elements.len(), &mut array)
+    })?;
+    for (i, element) in elements.iter().enumerate() {
+        check_status!(unsafe { sys::napi_set_element(env.raw(), array, i as u32, element.raw()) })?;
+    }
+    unsafe { JsUnknown::from_raw(env.raw(), array) }
+}
+
+/// Creates a Sendable object with `properties`. A Sendable object cannot get
+/// new properties after it has been created, so they are all passed at once.
+/// The keys must be distinct and the values Sendable.
+pub fn create_object(env: &Env, properties: Vec<(JsString, JsUnknown)>) -> Result<JsUnknown> {
+    let attributes = sys::PropertyAttributes::writable
+        | sys::PropertyAttributes::enumerable
+        | sys::PropertyAttributes::configurable;
+    let descriptors: Vec<sys::napi_property_descriptor> = properties
+        .iter()
+        .map(|(key, value)| sys::napi_property_descriptor {
+            utf8name: ptr::null(),
+            name: unsafe { key.raw() },
+            method: None,
+            getter: None,
+            setter: None,
+            value: unsafe { value.raw() },
+            attributes,
+            data: ptr::null_mut(),
+        })
+        .collect();
+    let mut object = ptr::null_mut();
+    check_status!(unsafe {
+        napi_create_sendable_object_with_properties(
+            env.raw(),
+            descriptors.len(),
+            descriptors.as_ptr(),
+            &mut object,
+        )
+    })?;
+    unsafe { JsUnknown::from_raw(env.raw(), object) }
+}
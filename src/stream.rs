@@ -0,0 +1,97 @@
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{ArrayBuffer, Either3, Uint8Array};
+use napi_ohos::{Env, Error, JsUnknown, Result, Status};
+
+use crate::builder::{JsBuilder, Reviver};
+use crate::error::{JsonErrorCode, ParseResult, with_code};
+use crate::options::Options;
+use crate::parse::{Compiled, JsonParser, Partial, check_value_options};
+use crate::value::{JsonValue, ValueBuilder};
+
+/// Parses a document from chunks, e.g. the body of an HTTP response, as they
+/// are written.
+///
+/// Each chunk is parsed when it is written, so an error is thrown by the
+/// `write()` of the chunk that has it. Only a token cut off by the end of a
+/// chunk is kept until the next one, and no JS string has to be built from the
+/// chunks. Values are built on the JS thread by `end()`, so `numberHandler` and
+/// `precisionLoss: "warn"` are not supported.
+#[napi]
+pub struct StreamingParser {
+    opts: Options,
+    compiled: Compiled,
+    /// Where parsing stopped, or `None` once it has ended or failed.
+    partial: Option<Partial<ValueBuilder>>,
+}
+
+#[napi]
+impl StreamingParser {
+    #[napi(constructor)]
+    pub fn new(options: Option<Options>) -> Result<Self> {
+        let opts = options.unwrap_or_default();
+        check_value_options(&opts, "StreamingParser")?;
+        let compiled = Compiled::new(&opts);
+        compiled.check()?;
+        Ok(StreamingParser {
+            opts,
+            compiled,
+            partial: Some(Partial::default()),
+        })
+    }
+
+    /// Parses a chunk. Strings are written as UTF-8, buffers must hold UTF-8.
+    #[napi]
+    pub fn write(
+        &mut self,
+        env: Env,
+        chunk: Either3<String, ArrayBuffer, Uint8Array>,
+    ) -> Result<()> {
+        let bytes: &[u8] = match &chunk {
+            Either3::A(s) => s.as_bytes(),
+            Either3::B(b) => b,
+            Either3::C(b) => b,
+        };
+        self.push(&env, bytes, false, "write() called after end()")?;
+        Ok(())
+    }
+
+    /// Checks that the document is complete and returns its value.
+    #[napi]
+    pub fn end(&mut self, env: Env, reviver: Option<Reviver<'_>>) -> Result<JsUnknown> {
+        let Some(value) = self.push(&env, &[], true, "end() called twice")? else {
+            unreachable!()
+        };
+        let build = || -> ParseResult<JsUnknown> {
+            let mut builder = JsBuilder::new(&env, &self.opts, reviver)?;
+            value.build(&mut builder)
+        };
+        build().map_err(|e| with_code(&env, e.into(), JsonErrorCode::Internal))
+    }
+
+    /// Parses `bytes` on from where the last push stopped. Parsing ends with the
+    /// `last` push or an error, after which nothing more can be pushed.
+    fn push(
+        &mut self,
+        env: &Env,
+        bytes: &[u8],
+        last: bool,
+        ended: &str,
+    ) -> Result<Option<JsonValue>> {
+        let Some(partial) = &mut self.partial else {
+            let error = Error::new(Status::GenericFailure, ended);
+            return Err(with_code(env, error, JsonErrorCode::InvalidState));
+        };
+        let result = JsonParser::push(
+            partial,
+            bytes,
+            last,
+            &self.opts,
+            &self.compiled,
+            ValueBuilder,
+        );
+        if last || result.is_err() {
+            self.partial = None;
+        }
+        result.map_err(|e| e.into_error(env))
+    }
+}
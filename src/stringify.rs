@@ -1,149 +1,1457 @@
-use std::fmt::Write;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use napi_derive_ohos::napi;
-use napi_ohos::bindgen_prelude::{FromNapiValue, JavaScriptClassExt};
+use napi_ohos::bindgen_prelude::{
+    AsyncTask, Either, FnArgs, FromNapiValue, Function, FunctionRef, JavaScriptClassExt,
+};
 use napi_ohos::{
-    Env, Error, JsBigInt, JsBoolean, JsNumber, JsObject, JsString, JsUnknown, NapiRaw, Result,
-    Status, ValueType,
+    Env, Error, JsArrayBuffer, JsBigInt, JsBoolean, JsFunction, JsNumber, JsObject, JsString,
+    JsTypedArray, JsUnknown, NapiRaw, Result, Status, Task, TypedArrayType, ValueType,
 };
-use widestring::{Utf16Str, Utf16String, utf16str};
+use widestring::{U16Str, U16String, Utf16String, utf16str};
 
 use crate::bignumber::BigNumber;
+use crate::case::KeyCase;
+use crate::error::{StringifyError, StringifyResult, take_exception};
+use crate::lossless::LosslessNumber;
+use crate::path::{PathSegment, to_pointer};
+use crate::writer::{Event, JsonWriter, Recorder, TextWriter};
+
+/// `(this: holder, key: string, value: unknown) => unknown`, as in `JSON.stringify`.
+type Replacer<'a> = Function<'a, FnArgs<(JsString, JsUnknown)>, JsUnknown>;
+
+/// `(value: number | bigint | BigNumber | LosslessNumber, path: (string | number)[]) => unknown`
+pub type NumberSerializer = FunctionRef<FnArgs<(JsUnknown, JsObject)>, JsUnknown>;
+
+/// `(path: (string | number)[], value: unknown) => unknown`
+pub type Transform = FunctionRef<FnArgs<(JsObject, JsUnknown)>, JsUnknown>;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct StringifyOptions {
+    /// Write an array or object that was already written, e.g. a shared or
+    /// circular reference, as `{"$ref": pointer}` instead of again, where
+    /// `pointer` is the JSON pointer of where it was first written. Parse with
+    /// `resolve_refs` to restore the references.
+    pub refs: Option<bool>,
+    /// How to write a TypedArray or ArrayBuffer, `"array"` by default.
+    pub binary: Option<BinaryFormat>,
+    /// The size in bytes of the chunks passed on by `stringify_chunked`, 64 KiB
+    /// by default.
+    pub chunk_size: Option<u32>,
+    /// Maximum nesting depth of arrays and objects, e.g. to fail fast on a
+    /// huge graph that is not meant to be written. Going deeper throws an error
+    /// with code `DEPTH_LIMIT` and the path of the array or object.
+    pub max_depth: Option<u32>,
+    /// Escape every character that is not ASCII as `\uXXXX`, for systems that
+    /// only take ASCII.
+    pub ensure_ascii: Option<bool>,
+    /// How to write a BigNumber, `"scientific"` by default.
+    pub big_number_notation: Option<BigNumberNotation>,
+    /// For `"auto"` notation, the exponent magnitude at which a BigNumber is
+    /// written in scientific notation. Without it, this is when the exponent is
+    /// at most -7 or at least 21, like a JS number.
+    pub exponential_at: Option<u32>,
+    /// How to write a BigInt, `"number"` by default.
+    pub bigint: Option<BigIntFormat>,
+    /// Called with every number, BigInt, BigNumber and LosslessNumber and the
+    /// keys and indices leading to it. Unless it returns `undefined`, the result
+    /// is converted to a string and written as is, in place of the number, so
+    /// it must be valid JSON, e.g. `"12.50"` or `"\"12.50\""`. The elements of a
+    /// TypedArray are not passed to it.
+    pub number_serializer: Option<NumberSerializer>,
+    /// How to write `NaN`, `Infinity` and `-Infinity`, `"null"` by default as
+    /// in `JSON.stringify`.
+    pub non_finite: Option<NonFiniteFormat>,
+    /// What goes between the elements of an array or the properties of an
+    /// object, `","` by default. It must keep the output JSON, e.g. `", "`.
+    pub item_separator: Option<String>,
+    /// What goes between a key and its value, `":"` by default, or `": "` with
+    /// `space`. It must keep the output JSON, e.g. `" : "`.
+    pub key_separator: Option<String>,
+    /// Write the properties of objects in sorted order rather than in the order
+    /// they were added, e.g. for snapshots that are diffed. `true` sorts keys
+    /// by their UTF-16 code units, or pass a comparator, `(a: string, b: string)
+    /// => number` as in `Array.prototype.sort`. The keys of an array replacer
+    /// keep its order.
+    pub sort_keys: Option<Either<bool, JsFunction>>,
+    /// Called with the keys and indices leading to each value, starting with
+    /// `[]` for the root, and the value after `toJSON` and the replacer. The
+    /// value is replaced with what it returns, e.g. `(path, value) => path[0]
+    /// === "prices" ? new BigNumber(value).div(100) : value`.
+    pub transform: Option<Transform>,
+    /// How to write an `Error`, `"object"` by default.
+    pub errors: Option<ErrorFormat>,
+    /// Leave out a property whose getter throws, or write `null` for such an
+    /// element, rather than throwing an error with code `GETTER_THREW` and the
+    /// path of the property.
+    pub skip_throwing_getters: Option<bool>,
+    /// Renames the keys of objects as they are written, to a `KeyCase`, e.g.
+    /// `"snake"` for a backend that expects `user_id` for `userId`, or with
+    /// `(key: string) => string`. The keys of an array replacer and of
+    /// `sort_keys` are the original ones.
+    pub key_transform: Option<Either<KeyCase, JsFunction>>,
+    /// Maximum size of the output in UTF-8 bytes, e.g. to keep a log line from
+    /// holding a huge object graph. Going over it throws an error with code
+    /// `OUTPUT_TOO_LARGE` as soon as a value ends past it.
+    pub max_output_bytes: Option<u32>,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// An array of the elements, or of the bytes of an ArrayBuffer.
+    Array,
+    /// A base64 string of the bytes.
+    Base64,
+    /// Throw an error.
+    Error,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Like any other object, which is usually `{}`, as in `JSON.stringify`.
+    Object,
+    /// `name`, `message` and `cause`, if it has one, then its enumerable
+    /// properties.
+    Message,
+    /// Like `"message"`, with `stack` after `message`.
+    Stack,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFiniteFormat {
+    Null,
+    /// `NaN`, `Infinity` or `-Infinity`, which is not JSON but which some
+    /// parsers accept.
+    Literal,
+    /// Throw an error.
+    Error,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigIntFormat {
+    /// The digits, unquoted, e.g. `12345678901234567890`.
+    Number,
+    /// The digits in a string, for consumers that only take numbers a double
+    /// can hold.
+    String,
+    /// Throw an error.
+    Error,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BigNumberNotation {
+    /// E.g. `1.5e1`.
+    Scientific,
+    /// A plain decimal, e.g. `15` or `0.000001`, however long it gets.
+    Plain,
+    /// Plain unless the exponent is large, see `exponential_at`.
+    Auto,
+}
+
+/// Like `JSON.stringify`, returns `undefined` for `undefined`, a function or a
+/// symbol.
+#[napi(ts_return_type = "string | undefined")]
+#[allow(dead_code)]
+pub fn stringify(
+    env: Env,
+    value: JsUnknown,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    #[napi(ts_arg_type = "number | string | undefined | null")] space: Option<JsUnknown>,
+    options: Option<StringifyOptions>,
+) -> Result<JsUnknown> {
+    let writer = text_writer(&env, space, options.as_ref())?;
+    let mut stringifier = create_stringifier(env, writer, replacer, options)?;
+    stringifier
+        .stringify(value)
+        .map_err(|e| e.into_error(&env, &stringifier.path_string()))
+}
+
+/// Like `stringify`, but returns the JSON as UTF-8 bytes, e.g. for a socket or
+/// a file, without creating a JS string.
+#[napi(ts_return_type = "ArrayBuffer | undefined")]
+#[allow(dead_code)]
+pub fn stringify_to_buffer(
+    env: Env,
+    value: JsUnknown,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    #[napi(ts_arg_type = "number | string | undefined | null")] space: Option<JsUnknown>,
+    options: Option<StringifyOptions>,
+) -> Result<JsUnknown> {
+    let writer = text_writer(&env, space, options.as_ref())?;
+    let mut stringifier = create_stringifier(env, writer, replacer, options)?;
+    let written = stringifier
+        .write_root(value)
+        .map_err(|e| e.into_error(&env, &stringifier.path_string()))?;
+    if !written {
+        return Ok(env.get_undefined()?.into_unknown());
+    }
+    let bytes = mem::take(&mut stringifier.writer.output).into_bytes();
+    Ok(env.create_arraybuffer_with_data(bytes)?.into_unknown())
+}
+
+/// `(chunk: ArrayBuffer) => void`
+type ChunkCallback<'a> = Function<'a, JsArrayBuffer, JsUnknown>;
+
+const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
 
+/// Like `stringify`, but passes the JSON to `callback` as UTF-8 bytes while it
+/// is written, in chunks of `chunk_size` bytes except for the last one, so the
+/// whole text is never held in memory.
 #[napi]
 #[allow(dead_code)]
-pub fn stringify(env: Env, value: JsUnknown) -> Result<JsString> {
-    let mut stringifier = JsonStringifier::new(env);
-    stringifier.stringify(value)
+pub fn stringify_chunked(
+    env: Env,
+    value: JsUnknown,
+    callback: ChunkCallback<'_>,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    #[napi(ts_arg_type = "number | string | undefined | null")] space: Option<JsUnknown>,
+    options: Option<StringifyOptions>,
+) -> Result<()> {
+    let chunk_size = options.as_ref().and_then(|o| o.chunk_size);
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 {
+        return Err(Error::new(Status::InvalidArg, "chunkSize must be positive"));
+    }
+    let writer = ChunkWriter {
+        text: text_writer(&env, space, options.as_ref())?,
+        bytes: Vec::new(),
+        chunk_size: chunk_size as usize,
+        env,
+        callback,
+    };
+    let mut stringifier = create_stringifier(env, writer, replacer, options)?;
+    let result = stringifier
+        .write_root(value)
+        .and_then(|_| stringifier.writer.finish());
+    result.map_err(|e| e.into_error(&env, &stringifier.path_string()))
 }
 
-struct JsonStringifier {
+/// Writes JSON text and passes it on in chunks of UTF-8, for `stringify_chunked`.
+struct ChunkWriter<'a> {
+    text: TextWriter,
+    /// The UTF-8 of the text written so far that has not been passed on.
+    bytes: Vec<u8>,
+    chunk_size: usize,
     env: Env,
+    callback: ChunkCallback<'a>,
 }
 
-impl JsonStringifier {
-    fn new(env: Env) -> Self {
-        JsonStringifier { env }
+impl ChunkWriter<'_> {
+    /// Moves the text written so far into `bytes`.
+    fn take_text(&mut self) {
+        self.bytes.extend_from_slice(self.text.output.as_bytes());
+        self.text.clear();
     }
 
-    fn stringify(&mut self, value: JsUnknown) -> Result<JsString> {
-        let mut output = Utf16String::with_capacity(1024);
-        self.write_value(&mut output, value)?;
-        self.env.create_string_utf16(output.as_slice())
+    fn emit(&mut self, chunk: Vec<u8>) -> StringifyResult<()> {
+        let chunk = self.env.create_arraybuffer_with_data(chunk)?.into_raw();
+        self.callback.call(chunk)?;
+        Ok(())
     }
 
-    fn write_value(&mut self, output: &mut Utf16String, value: JsUnknown) -> Result<()> {
-        match value.get_type()? {
-            ValueType::String => self.write_string(output, unsafe { value.cast() }),
-            ValueType::BigInt => self.write_bigint(output, unsafe { value.cast() }),
-            ValueType::Object => self.write_object(output, unsafe { value.cast() }),
-            ValueType::Number => self.write_number(output, unsafe { value.cast() }),
-            ValueType::Boolean => self.write_boolean(output, unsafe { value.cast() }),
-            _ => self.write_null(output),
+    /// Passes on the rest of the text.
+    fn finish(&mut self) -> StringifyResult<()> {
+        self.flush()?;
+        self.take_text();
+        let rest = mem::take(&mut self.bytes);
+        if !rest.is_empty() {
+            self.emit(rest)?;
         }
+        Ok(())
+    }
+}
+
+impl JsonWriter for ChunkWriter<'_> {
+    fn null(&mut self) {
+        self.text.null();
+    }
+
+    fn boolean(&mut self, value: bool) {
+        self.text.boolean(value);
+    }
+
+    fn number(&mut self, text: &str) {
+        self.text.number(text);
+    }
+
+    fn string(&mut self, value: &U16Str) {
+        self.text.string(value);
+    }
+
+    fn raw(&mut self, json: &str) {
+        self.text.raw(json);
     }
 
-    fn write_null(&self, output: &mut Utf16String) -> Result<()> {
-        output.push_utfstr(utf16str!("null"));
+    fn begin_array(&mut self) {
+        self.text.begin_array();
+    }
+
+    fn end_array(&mut self) {
+        self.text.end_array();
+    }
+
+    fn begin_object(&mut self) {
+        self.text.begin_object();
+    }
+
+    fn key(&mut self, key: &U16Str) {
+        self.text.key(key);
+    }
+
+    fn end_object(&mut self) {
+        self.text.end_object();
+    }
+
+    fn flush(&mut self) -> StringifyResult<()> {
+        self.text.flush()?;
+        if self.bytes.len() + self.text.output.len() < self.chunk_size {
+            return Ok(());
+        }
+        self.take_text();
+        while self.bytes.len() >= self.chunk_size {
+            let rest = self.bytes.split_off(self.chunk_size);
+            let chunk = mem::replace(&mut self.bytes, rest);
+            self.emit(chunk)?;
+        }
         Ok(())
     }
+}
+
+/// Like `stringify`, but writes the JSON text on a worker thread.
+///
+/// Only the writing is moved off the JS thread. `value` is read before the
+/// promise is returned, with every getter, `toJSON` and replacer call, and the
+/// caller waits for that as for `stringify`. This helps when the text is large,
+/// not when reading the value is what takes long.
+#[napi(ts_return_type = "Promise<string | undefined>")]
+#[allow(dead_code)]
+pub fn stringify_async(
+    env: Env,
+    value: JsUnknown,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    #[napi(ts_arg_type = "number | string | undefined | null")] space: Option<JsUnknown>,
+    options: Option<StringifyOptions>,
+) -> Result<AsyncTask<StringifyTask>> {
+    let writer = text_writer(&env, space, options.as_ref())?;
+    let events = record(env, value, replacer, options)?;
+    Ok(AsyncTask::new(StringifyTask { events, writer }))
+}
+
+/// What was read of a value to write on a worker thread. The error reading
+/// it is kept until the promise is settled, so that the promise is rejected
+/// with it.
+type Recorded = std::result::Result<Vec<Event>, Error>;
+
+/// Reads `value` on the JS thread, for writing on a worker thread. Stops with
+/// `OUTPUT_TOO_LARGE` as soon as what was read is sure to exceed `max_output_bytes`.
+fn record(
+    env: Env,
+    value: JsUnknown,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    options: Option<StringifyOptions>,
+) -> Result<Recorded> {
+    let max_len = options.as_ref().and_then(|o| o.max_output_bytes);
+    let recorder = Recorder::new(max_len.map(|max| max as usize));
+    let mut stringifier = create_stringifier(env, recorder, replacer, options)?;
+    // Nothing is recorded for a value that has no JSON.
+    Ok(match stringifier.write_root(value) {
+        Ok(_) => Ok(mem::take(&mut stringifier.writer.events)),
+        // The error is created now, while a JS exception is still pending.
+        Err(e) => Err(e.into_error(&env, &stringifier.path_string())),
+    })
+}
+
+/// Writes what `stringify_async` read on a worker thread.
+pub struct StringifyTask {
+    events: Recorded,
+    writer: TextWriter,
+}
+
+impl Task for StringifyTask {
+    type Output = StringifyResult<Option<String>>;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let events = mem::replace(&mut self.events, Ok(Vec::new()))?;
+        if events.is_empty() {
+            return Ok(Ok(None));
+        }
+        for event in &events {
+            event.replay(&mut self.writer);
+            if let Err(e) = self.writer.flush() {
+                return Ok(Err(e));
+            }
+        }
+        Ok(Ok(Some(mem::take(&mut self.writer.output))))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<JsUnknown> {
+        match output.map_err(|e| e.into_error(&env, ""))? {
+            Some(output) => Ok(env.create_string(&output)?.into_unknown()),
+            None => Ok(env.get_undefined()?.into_unknown()),
+        }
+    }
+}
+
+/// Like `stringify_async`, but writes the JSON as UTF-8 to the file at `path`.
+/// The value is still read on the JS thread first, but the text is written in
+/// chunks as it is made rather than held whole. It goes to a temporary file
+/// next to `path`, which replaces the file once it is complete and is removed
+/// on an error. A value that has no JSON, e.g. `undefined`, is an error.
+#[napi(ts_return_type = "Promise<void>")]
+#[allow(dead_code)]
+pub fn stringify_to_file(
+    env: Env,
+    path: String,
+    value: JsUnknown,
+    replacer: Option<Either<Replacer<'_>, Vec<JsUnknown>>>,
+    #[napi(ts_arg_type = "number | string | undefined | null")] space: Option<JsUnknown>,
+    options: Option<StringifyOptions>,
+) -> Result<AsyncTask<StringifyFileTask>> {
+    let writer = text_writer(&env, space, options.as_ref())?;
+    let events = match record(env, value, replacer, options)? {
+        Ok(events) if events.is_empty() => {
+            let error = StringifyError::UnsupportedValue("undefined, a function or a symbol");
+            Err(error.into_error(&env, ""))
+        }
+        events => events,
+    };
+    Ok(AsyncTask::new(StringifyFileTask {
+        events,
+        writer,
+        path: PathBuf::from(path),
+    }))
+}
+
+/// How much text is written to a file at a time, in bytes.
+const FILE_CHUNK_LEN: usize = 64 * 1024;
+
+/// The number of temporary files made by this process so far, which keeps
+/// their names apart when several writes go to the same path.
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
 
-    fn write_boolean(&self, output: &mut Utf16String, value: JsBoolean) -> Result<()> {
-        let v = if value.get_value()? {
-            utf16str!("true")
+/// Writes what `stringify_to_file` read to the file on a worker thread.
+pub struct StringifyFileTask {
+    events: Recorded,
+    writer: TextWriter,
+    path: PathBuf,
+}
+
+impl StringifyFileTask {
+    /// Writes the JSON of `events` to a new file at `path`. The outer error is
+    /// from the file, the inner one from `max_output_bytes`.
+    fn write(&mut self, path: &Path, events: &[Event]) -> io::Result<StringifyResult<()>> {
+        let mut file = File::create_new(path)?;
+        for event in events {
+            event.replay(&mut self.writer);
+            if let Err(e) = self.writer.flush() {
+                return Ok(Err(e));
+            }
+            if self.writer.output.len() >= FILE_CHUNK_LEN {
+                file.write_all(self.writer.output.as_bytes())?;
+                self.writer.clear();
+            }
+        }
+        file.write_all(self.writer.output.as_bytes())?;
+        file.sync_all()?;
+        Ok(Ok(()))
+    }
+}
+
+impl Task for StringifyFileTask {
+    type Output = StringifyResult<()>;
+    type JsValue = ();
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let events = mem::replace(&mut self.events, Ok(Vec::new()))?;
+        let n = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(format!(".{}.{n}.tmp", process::id()));
+        let temp = PathBuf::from(temp);
+        let path = self.path.clone();
+        let result = self.write(&temp, &events).and_then(|written| {
+            if written.is_ok() {
+                fs::rename(&temp, &path)?;
+            }
+            Ok(written)
+        });
+        match result {
+            Ok(Ok(())) => Ok(Ok(())),
+            Ok(Err(e)) => {
+                let _ = fs::remove_file(&temp);
+                Ok(Err(e))
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                Err(Error::new(
+                    Status::GenericFailure,
+                    format!("Failed to write {}: {e}", path.display()),
+                ))
+            }
+        }
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<()> {
+        output.map_err(|e| e.into_error(&env, ""))
+    }
+}
+
+/// A `TextWriter` for the `space` argument and `options` of `stringify`.
+fn text_writer(
+    env: &Env,
+    space: Option<JsUnknown>,
+    options: Option<&StringifyOptions>,
+) -> Result<TextWriter> {
+    let mut writer = TextWriter::new(gap(env, space)?);
+    let Some(opts) = options else {
+        return Ok(writer);
+    };
+    writer.ensure_ascii = opts.ensure_ascii.unwrap_or(false);
+    writer.max_len = opts.max_output_bytes.map(|max| max as usize);
+    if let Some(separator) = &opts.item_separator {
+        writer.item_separator = separator.clone();
+    }
+    if let Some(separator) = &opts.key_separator {
+        writer.key_separator = separator.clone();
+    }
+    Ok(writer)
+}
+
+/// The longest indentation `space` can give, as in `JSON.stringify`.
+const MAX_GAP: usize = 10;
+
+/// The indentation given by the `space` argument of `stringify`, following
+/// `JSON.stringify`: a number of spaces, or a string such as `"\t"`, both cut to
+/// 10 characters. A `Number` or `String` object counts as its value, and
+/// anything else, e.g. `true`, means no indentation.
+fn gap(env: &Env, space: Option<JsUnknown>) -> Result<String> {
+    let Some(mut space) = space else {
+        return Ok(String::new());
+    };
+    if space.get_type()? == ValueType::Object {
+        let global = env.get_global()?;
+        let number: JsFunction = global.get_named_property("Number")?;
+        let string: JsFunction = global.get_named_property("String")?;
+        if space.instanceof(number)? {
+            space = space.coerce_to_number()?.into_unknown();
+        } else if space.instanceof(string)? {
+            space = space.coerce_to_string()?.into_unknown();
+        }
+    }
+    match space.get_type()? {
+        ValueType::Number => {
+            // Truncated toward zero, with `NaN` and negative numbers as 0.
+            let n = space.coerce_to_number()?.get_double()?;
+            Ok(" ".repeat(n.clamp(0.0, MAX_GAP as f64) as usize))
+        }
+        ValueType::String => {
+            let utf16_c = space.coerce_to_string()?.into_utf16()?;
+            let (_, s) = utf16_c.as_slice().split_last().unwrap();
+            Ok(String::from_utf16_lossy(&s[..s.len().min(MAX_GAP)]))
+        }
+        _ => Ok(String::new()),
+    }
+}
+
+/// Sets up a `JsonStringifier` from the other arguments of `stringify`.
+fn create_stringifier<'a, W: JsonWriter>(
+    env: Env,
+    writer: W,
+    replacer: Option<Either<Replacer<'a>, Vec<JsUnknown>>>,
+    options: Option<StringifyOptions>,
+) -> Result<JsonStringifier<'a, W>> {
+    let opts = options.unwrap_or_default();
+    let (replacer, allowed) = match replacer {
+        Some(Either::A(f)) => (Some(f), None),
+        Some(Either::B(keys)) => (None, Some(keys)),
+        None => (None, None),
+    };
+    let mut stringifier = JsonStringifier::new(env, replacer, writer);
+    if let Some(keys) = allowed {
+        stringifier.allowed_keys = Some(allowed_keys(&env, keys)?);
+    }
+    stringifier.binary = opts.binary.unwrap_or(BinaryFormat::Array);
+    stringifier.max_depth = opts.max_depth;
+    stringifier.notation = opts
+        .big_number_notation
+        .unwrap_or(BigNumberNotation::Scientific);
+    stringifier.exponential_at = opts.exponential_at;
+    stringifier.bigint = opts.bigint.unwrap_or(BigIntFormat::Number);
+    stringifier.number_serializer = opts.number_serializer;
+    stringifier.non_finite = opts.non_finite.unwrap_or(NonFiniteFormat::Null);
+    stringifier.sort_keys = opts.sort_keys;
+    stringifier.transform = opts.transform;
+    stringifier.errors = opts.errors.unwrap_or(ErrorFormat::Object);
+    stringifier.skip_throwing_getters = opts.skip_throwing_getters.unwrap_or(false);
+    stringifier.key_transform = opts.key_transform;
+    if opts.refs.is_some_and(|e| e) {
+        stringifier.refs = Some(Refs::new(&env)?);
+    }
+    Ok(stringifier)
+}
+
+/// Writes `value` as canonical JSON (RFC 8785), for signing and hashing: no
+/// whitespace, keys sorted by their UTF-16 code units, numbers written as in
+/// JavaScript and only the escapes strings need. A BigInt, BigNumber,
+/// LosslessNumber, `NaN` or infinity has no canonical form and is an error, and
+/// so is a string or key with an unpaired surrogate, as RFC 8785 only allows
+/// valid Unicode.
+#[napi(ts_return_type = "string | undefined")]
+#[allow(dead_code)]
+pub fn stringify_canonical(env: Env, value: JsUnknown) -> Result<JsUnknown> {
+    let mut writer = TextWriter::new(String::new());
+    writer.lowercase_hex = true;
+    let mut stringifier = JsonStringifier::new(env, None, writer);
+    stringifier.canonical = true;
+    stringifier
+        .stringify(value)
+        .map_err(|e| e.into_error(&env, &stringifier.path_string()))
+}
+
+/// Writes the elements of `values` as a JSON text sequence (RFC 7464), each one
+/// preceded by a record separator and followed by a line feed.
+#[napi(ts_args_type = "values: Array<unknown>")]
+#[allow(dead_code)]
+pub fn stringify_seq(env: Env, values: JsObject) -> Result<JsString> {
+    let writer = TextWriter::new(String::new());
+    let mut stringifier = JsonStringifier::new(env, None, writer);
+    stringifier
+        .stringify_seq(values)
+        .map_err(|e| e.into_error(&env, &stringifier.path_string()))
+}
+
+/// The keys of an array replacer as JS strings, without repeats. As in
+/// `JSON.stringify`, numbers and `Number` and `String` objects are converted
+/// to strings, and elements of other types are skipped.
+fn allowed_keys(env: &Env, keys: Vec<JsUnknown>) -> Result<Vec<JsString>> {
+    let global = env.get_global()?;
+    let number: JsObject = global.get_named_property("Number")?;
+    let string: JsObject = global.get_named_property("String")?;
+    let mut seen = HashSet::new();
+    let mut strings = Vec::new();
+    for key in keys {
+        let key = match key.get_type()? {
+            ValueType::String | ValueType::Number => key.coerce_to_string()?,
+            ValueType::Object if key.instanceof(number)? || key.instanceof(string)? => {
+                key.coerce_to_string()?
+            }
+            _ => continue,
+        };
+        if seen.insert(key.into_utf16()?.as_slice().to_vec()) {
+            strings.push(key);
+        }
+    }
+    Ok(strings)
+}
+
+/// A JS `Map` from each array and object written so far to the JSON pointer of
+/// where it was written, for `refs`.
+struct Refs {
+    map: JsObject,
+    get: JsFunction,
+    set: JsFunction,
+}
+
+impl Refs {
+    fn new(env: &Env) -> Result<Self> {
+        let ctor: JsFunction = env.get_global()?.get_named_property("Map")?;
+        let map = ctor.new_instance::<JsUnknown>(&[])?;
+        let prototype: JsObject = ctor.coerce_to_object()?.get_named_property("prototype")?;
+        Ok(Refs {
+            map,
+            get: prototype.get_named_property("get")?,
+            set: prototype.get_named_property("set")?,
+        })
+    }
+}
+
+/// Where a value is in its parent, for the replacer and error paths.
+#[derive(Clone, Copy)]
+enum Key {
+    Index(u32),
+    Name(JsString),
+}
+
+/// What is left to write of an array or object.
+enum Children {
+    /// The length of an array.
+    Elements(u32),
+    /// The keys of an object to write, in order.
+    Keys(Vec<JsString>),
+}
+
+/// An array or object being written.
+struct Frame {
+    obj: JsObject,
+    children: Children,
+    /// How many of `children` have been taken.
+    next: u32,
+}
+
+/// Reads JS values the way `JSON.stringify` does and passes what it finds to a
+/// `JsonWriter`.
+struct JsonStringifier<'a, W> {
+    env: Env,
+    writer: W,
+    replacer: Option<Replacer<'a>>,
+    /// The only keys written for each object, in this order, from an array replacer.
+    allowed_keys: Option<Vec<JsString>>,
+    /// The arrays and objects being written, from the root down. They are kept
+    /// here rather than on the native stack, so deep nesting can't overflow it.
+    stack: Vec<Frame>,
+    max_depth: Option<u32>,
+    /// The keys and indices leading to the value being written.
+    path: Vec<Key>,
+    refs: Option<Refs>,
+    binary: BinaryFormat,
+    notation: BigNumberNotation,
+    exponential_at: Option<u32>,
+    bigint: BigIntFormat,
+    number_serializer: Option<NumberSerializer>,
+    non_finite: NonFiniteFormat,
+    sort_keys: Option<Either<bool, JsFunction>>,
+    transform: Option<Transform>,
+    errors: ErrorFormat,
+    skip_throwing_getters: bool,
+    key_transform: Option<Either<KeyCase, JsFunction>>,
+    /// `JSON.isRawJSON`, if the engine has it.
+    is_raw_json: Option<JsFunction>,
+    /// For `stringify_canonical`.
+    canonical: bool,
+}
+
+impl JsonStringifier<'_, TextWriter> {
+    /// Returns the JSON of `value`, or `undefined` if it has none.
+    fn stringify(&mut self, value: JsUnknown) -> StringifyResult<JsUnknown> {
+        if !self.write_root(value)? {
+            return Ok(self.env.get_undefined()?.into_unknown());
+        }
+        Ok(self.env.create_string(&self.writer.output)?.into_unknown())
+    }
+
+    fn stringify_seq(&mut self, values: JsObject) -> StringifyResult<JsString> {
+        let length = values.get_array_length()?;
+        for i in 0..length {
+            let value: JsUnknown = values.get_element_unchecked(i)?;
+            let value = self.to_json(Key::Index(i), value)?;
+            self.writer.output.push('\u{1E}');
+            self.path.push(Key::Index(i));
+            self.write_value(value)?;
+            self.path.pop();
+            self.writer.output.push('\n');
+        }
+        Ok(self.env.create_string(&self.writer.output)?)
+    }
+}
+
+impl<'a, W: JsonWriter> JsonStringifier<'a, W> {
+    fn new(env: Env, replacer: Option<Replacer<'a>>, writer: W) -> Self {
+        JsonStringifier {
+            env,
+            writer,
+            replacer,
+            allowed_keys: None,
+            stack: Vec::new(),
+            max_depth: None,
+            path: Vec::new(),
+            refs: None,
+            binary: BinaryFormat::Array,
+            notation: BigNumberNotation::Scientific,
+            exponential_at: None,
+            bigint: BigIntFormat::Number,
+            number_serializer: None,
+            non_finite: NonFiniteFormat::Null,
+            sort_keys: None,
+            transform: None,
+            errors: ErrorFormat::Object,
+            skip_throwing_getters: false,
+            key_transform: None,
+            is_raw_json: is_raw_json_function(&env),
+            canonical: false,
+        }
+    }
+
+    /// The path to the value being written, e.g. `items[3].price`.
+    fn path_string(&self) -> String {
+        let mut path = String::new();
+        for key in &self.path {
+            match key {
+                Key::Index(i) => path.push_str(&format!("[{i}]")),
+                Key::Name(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    let name = name.into_utf8().and_then(|s| s.into_owned());
+                    path.push_str(&name.unwrap_or_default());
+                }
+            }
+        }
+        path
+    }
+
+    /// The JSON pointer of the value being written, for `refs`.
+    fn pointer(&self) -> Result<String> {
+        let mut segments = Vec::with_capacity(self.path.len());
+        for key in &self.path {
+            segments.push(match key {
+                Key::Index(i) => PathSegment::Index(*i),
+                Key::Name(name) => {
+                    PathSegment::Key(U16String::from(name.into_utf8()?.into_owned()?))
+                }
+            });
+        }
+        Ok(to_pointer(&segments))
+    }
+
+    /// The keys and indices leading to the value being written, as a JS array.
+    fn path_array(&self) -> Result<JsObject> {
+        let mut keys = self.env.create_array_with_length(self.path.len())?;
+        for (i, key) in self.path.iter().enumerate() {
+            match key {
+                Key::Index(index) => keys.set_element(i as u32, *index)?,
+                Key::Name(name) => keys.set_element(i as u32, *name)?,
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Writes what the `number_serializer`, if any, returns for `value`, a
+    /// number, BigInt, BigNumber or LosslessNumber. Returns whether it wrote
+    /// anything.
+    fn serialize_number(&mut self, value: JsUnknown) -> StringifyResult<bool> {
+        let Some(serializer) = &self.number_serializer else {
+            return Ok(false);
+        };
+        let path = self.path_array()?;
+        let result = serializer
+            .borrow_back(&self.env)?
+            .call((value, path).into())?;
+        if result.get_type()? == ValueType::Undefined {
+            return Ok(false);
+        }
+        let text = result.coerce_to_string()?.into_utf8()?;
+        self.writer.number(text.as_str()?);
+        Ok(true)
+    }
+
+    /// Writes `obj` as `{"$ref": pointer}` if it was already written, for `refs`.
+    /// Otherwise remembers where it is written and returns `false`.
+    fn write_ref(&mut self, obj: JsObject) -> StringifyResult<bool> {
+        let Some(refs) = &self.refs else {
+            return Ok(false);
+        };
+        let pointer = refs.get.call(Some(&refs.map), &[obj.into_unknown()])?;
+        if pointer.get_type()? == ValueType::String {
+            self.writer.begin_object();
+            self.writer.key(utf16str!("$ref").as_ustr());
+            self.write_string(unsafe { pointer.cast() })?;
+            self.writer.end_object();
+            return Ok(true);
+        }
+        let pointer = self.env.create_string(&self.pointer()?)?;
+        refs.set.call(
+            Some(&refs.map),
+            &[obj.into_unknown(), pointer.into_unknown()],
+        )?;
+        Ok(false)
+    }
+
+    /// Checks that `obj` can be written in the arrays and objects being written:
+    /// it must not be one of them, and not be nested deeper than `max_depth`.
+    fn enter(&self, obj: JsObject) -> StringifyResult<()> {
+        match self.max_depth {
+            Some(max) if self.stack.len() >= max as usize => {
+                return Err(StringifyError::DepthLimitExceeded(max));
+            }
+            _ => {}
+        }
+        for frame in &self.stack {
+            if self.env.strict_equals(frame.obj, obj)? {
+                return Err(StringifyError::CircularStructure);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the JSON of `value`, the root. Like `JSON.stringify`, writes
+    /// nothing and returns `false` if it is `undefined`, a function or a symbol,
+    /// after `toJSON`, the replacer and `transform`.
+    fn write_root(&mut self, value: JsUnknown) -> StringifyResult<bool> {
+        let value = if self.replacer.is_some() {
+            // Like `JSON.stringify`, the root is passed to the replacer as the
+            // `""` property of a new object.
+            let mut holder = self.env.create_object()?;
+            holder.set_named_property("", value)?;
+            let key = self.env.create_string("")?;
+            self.replace(holder, Key::Name(key), value)?
         } else {
-            utf16str!("false")
+            let key = self.env.create_string("")?;
+            let value = self.to_json(Key::Name(key), value)?;
+            self.transform(value)?
+        };
+        if is_unwritable(value)? {
+            return Ok(false);
+        }
+        self.write_value(value)?;
+        Ok(true)
+    }
+
+    /// Calls `toJSON`, the replacer and `transform`, if any, on the value at
+    /// `key` of `holder`.
+    fn replace(&self, holder: JsObject, key: Key, value: JsUnknown) -> StringifyResult<JsUnknown> {
+        let value = self.to_json(key, value)?;
+        let value = match &self.replacer {
+            Some(replacer) => {
+                let key = self.key_string(key)?;
+                replacer.apply(holder, (key, value).into())?
+            }
+            None => value,
+        };
+        self.transform(value)
+    }
+
+    /// Calls `transform`, if any, on `value`, the value being written.
+    fn transform(&self, value: JsUnknown) -> StringifyResult<JsUnknown> {
+        let Some(transform) = &self.transform else {
+            return Ok(value);
+        };
+        let path = self.path_array()?;
+        Ok(transform
+            .borrow_back(&self.env)?
+            .call((path, value).into())?)
+    }
+
+    /// Calls the `toJSON` method of `value`, if it has one, with `key`, as
+    /// `JSON.stringify` does for e.g. a `Date`. The `toJSON` of a `BigNumber` or
+    /// `LosslessNumber` is skipped, since they are written as numbers.
+    fn to_json(&self, key: Key, value: JsUnknown) -> StringifyResult<JsUnknown> {
+        if !matches!(value.get_type()?, ValueType::Object | ValueType::BigInt) {
+            return Ok(value);
+        }
+        let obj = value.coerce_to_object()?;
+        let method: JsUnknown = obj.get_named_property("toJSON")?;
+        if method.get_type()? != ValueType::Function
+            || BigNumber::instance_of(self.env, &obj)?
+            || LosslessNumber::instance_of(self.env, &obj)?
+        {
+            return Ok(value);
+        }
+        let method: JsFunction = unsafe { method.cast() };
+        let key = self.key_string(key)?;
+        Ok(method.call(Some(&obj), &[key])?)
+    }
+
+    fn key_string(&self, key: Key) -> Result<JsString> {
+        match key {
+            Key::Index(i) => self.env.create_string(&i.to_string()),
+            Key::Name(key) => Ok(key),
+        }
+    }
+
+    /// Writes `value` and everything in it.
+    fn write_value(&mut self, mut value: JsUnknown) -> StringifyResult<()> {
+        let base = self.stack.len();
+        loop {
+            let depth = self.stack.len();
+            self.write_one(value)?;
+            if self.stack.len() == depth {
+                self.writer.flush()?;
+            }
+            // Find the next value, ending the arrays and objects that are done.
+            loop {
+                if self.stack.len() == base {
+                    return Ok(());
+                }
+                if let Some(next) = self.next_child()? {
+                    value = next;
+                    break;
+                }
+                self.end()?;
+            }
+        }
+    }
+
+    /// Takes the next element, or the next writable property, of the innermost
+    /// array or object being written and writes its key. Returns `None` at the end.
+    fn next_child(&mut self) -> StringifyResult<Option<JsUnknown>> {
+        loop {
+            let Some(frame) = self.stack.last_mut() else {
+                return Ok(None);
+            };
+            let (obj, i) = (frame.obj, frame.next);
+            let key = match &frame.children {
+                Children::Elements(length) if i < *length => Key::Index(i),
+                Children::Keys(keys) if (i as usize) < keys.len() => Key::Name(keys[i as usize]),
+                _ => return Ok(None),
+            };
+            frame.next += 1;
+            if i > 0 {
+                self.path.pop();
+            }
+            self.path.push(key);
+
+            let Some(value) = self.get(obj, key)? else {
+                match key {
+                    Key::Index(_) => return Ok(Some(self.env.get_undefined()?.into_unknown())),
+                    Key::Name(_) => continue,
+                }
+            };
+            let value = self.replace(obj, key, value)?;
+            match key {
+                // Like `JSON.stringify`, a hole, e.g. in `[1, , 3]`, is `undefined`
+                // here, and it and any other unwritable element are `null`.
+                Key::Index(_) => return Ok(Some(value)),
+                // Like `JSON.stringify`, a property that is `undefined`, a function
+                // or a symbol, e.g. because the replacer returned it, is left out.
+                Key::Name(_) if is_unwritable(value)? => continue,
+                Key::Name(name) => {
+                    self.write_key(name)?;
+                    return Ok(Some(value));
+                }
+            }
+        }
+    }
+
+    /// Writes `name`, renamed by `key_transform`, as the key of the next value.
+    fn write_key(&mut self, name: JsString) -> StringifyResult<()> {
+        let name = match &self.key_transform {
+            Some(Either::B(rename)) => rename.call(None, &[name])?.coerce_to_string()?,
+            _ => name,
         };
-        output.push_utfstr(v);
+        let utf16_c = name.into_utf16()?;
+        let (_, name) = utf16_c.as_slice().split_last().unwrap();
+        self.check_unicode(name)?;
+        match &self.key_transform {
+            Some(Either::A(case)) => {
+                let name = case.convert(name);
+                self.writer.key(U16Str::from_slice(&name));
+            }
+            _ => self.writer.key(U16Str::from_slice(name)),
+        }
         Ok(())
     }
 
-    fn write_number(&self, output: &mut Utf16String, value: JsNumber) -> Result<()> {
-        let utf16_c = value.coerce_to_string()?.into_utf16()?;
-        let (_, s) = utf16_c.as_slice().split_last().unwrap();
-        let s = unsafe { Utf16Str::from_slice_unchecked(s) };
-        output.push_utfstr(s);
+    /// Reads the value at `key` of `obj`. If a getter throws, returns `None` for
+    /// `skip_throwing_getters`, and otherwise an error with what it threw.
+    fn get(&self, obj: JsObject, key: Key) -> StringifyResult<Option<JsUnknown>> {
+        let value = match key {
+            Key::Index(i) => obj.get_element_unchecked(i),
+            Key::Name(name) => obj.get_property_unchecked(name),
+        };
+        match value {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.status == Status::PendingException => {
+                let thrown = take_exception(&self.env)?;
+                if self.skip_throwing_getters {
+                    return Ok(None);
+                }
+                let thrown = thrown.coerce_to_string()?.into_utf8()?.into_owned()?;
+                Err(StringifyError::GetterThrew(thrown))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Ends the innermost array or object being written.
+    fn end(&mut self) -> StringifyResult<()> {
+        let Some(frame) = self.stack.pop() else {
+            return Ok(());
+        };
+        if frame.next > 0 {
+            self.path.pop();
+        }
+        match frame.children {
+            Children::Elements(_) => self.writer.end_array(),
+            Children::Keys(_) => self.writer.end_object(),
+        }
+        self.writer.flush()
+    }
+
+    /// Writes a value that is not an array or object, or begins one and pushes
+    /// it to `stack`.
+    fn write_one(&mut self, value: JsUnknown) -> StringifyResult<()> {
+        let value_type = value.get_type()?;
+        if matches!(value_type, ValueType::Number | ValueType::BigInt)
+            && self.serialize_number(value)?
+        {
+            return Ok(());
+        }
+        match value_type {
+            ValueType::String => self.write_string(unsafe { value.cast() })?,
+            ValueType::BigInt => self.write_bigint(unsafe { value.cast() })?,
+            ValueType::Object => self.write_object(unsafe { value.cast() })?,
+            ValueType::Number => self.write_number(unsafe { value.cast() })?,
+            ValueType::Boolean => self.write_boolean(unsafe { value.cast() })?,
+            // An unwritable value, which is left out of objects but is `null` in arrays.
+            _ => self.writer.null(),
+        }
         Ok(())
     }
 
-    fn write_string(&self, output: &mut Utf16String, value: JsString) -> Result<()> {
-        let utf16_c = value.into_utf16()?;
-        let (_, s) = utf16_c.as_slice().split_last().unwrap();
-        let s = unsafe { Utf16Str::from_slice_unchecked(s) };
-        output.reserve(2 + s.len());
-
-        output.push_utfstr(utf16str!("\""));
-        for c in s.chars() {
-            match c {
-                '"' => output.push_utfstr(utf16str!(r#"\""#)),
-                '\\' => output.push_utfstr(utf16str!(r"\\")),
-                '\n' => output.push_utfstr(utf16str!(r"\n")),
-                '\r' => output.push_utfstr(utf16str!(r"\r")),
-                '\t' => output.push_utfstr(utf16str!(r"\t")),
-                '\u{0008}' => output.push_utfstr(utf16str!(r"\b")),
-                '\u{000C}' => output.push_utfstr(utf16str!(r"\f")),
-                '\u{0000}'..='\u{001F}' => {
-                    write!(output, "\\u{:04X}", c as u32)
-                        .map_err(|e| Error::new(Status::GenericFailure, e))?;
+    fn write_boolean(&mut self, value: JsBoolean) -> StringifyResult<()> {
+        self.writer.boolean(value.get_value()?);
+        Ok(())
+    }
+
+    fn write_number(&mut self, value: JsNumber) -> StringifyResult<()> {
+        self.write_double(value.get_double()?)
+    }
+
+    /// Writes `value` as `Number.prototype.toString` would, without calling it.
+    fn write_double(&mut self, value: f64) -> StringifyResult<()> {
+        if !value.is_finite() {
+            match self.non_finite {
+                _ if self.canonical => {
+                    return Err(StringifyError::UnsupportedValue("a non-finite number"));
+                }
+                NonFiniteFormat::Null => {
+                    self.writer.null();
+                    return Ok(());
+                }
+                NonFiniteFormat::Literal => {}
+                NonFiniteFormat::Error => {
+                    return Err(StringifyError::UnsupportedValue("a non-finite number"));
                 }
-                _ => output.push(c),
             }
         }
-        output.push_utfstr(utf16str!("\""));
+        if value.fract() == 0.0 && value.abs() < MAX_EXACT_INTEGER {
+            // Also writes `-0` as `0`, like JS.
+            self.writer.number(itoa::Buffer::new().format(value as i64));
+        } else {
+            // The shortest digits that read back as `value`, formatted as in JS,
+            // e.g. `1.5`, `1e+21` or `NaN`.
+            self.writer.number(ryu_js::Buffer::new().format(value));
+        }
+        Ok(())
+    }
+
+    fn write_string(&mut self, value: JsString) -> StringifyResult<()> {
+        let utf16_c = value.into_utf16()?;
+        let (_, s) = utf16_c.as_slice().split_last().unwrap();
+        self.check_unicode(s)?;
+        self.writer.string(U16Str::from_slice(s));
+        Ok(())
+    }
+
+    /// For `canonical`, fails on an unpaired surrogate, which `JSON.stringify`
+    /// would escape.
+    fn check_unicode(&self, s: &[u16]) -> StringifyResult<()> {
+        if self.canonical && char::decode_utf16(s.iter().copied()).any(|c| c.is_err()) {
+            return Err(StringifyError::UnsupportedValue("a lone surrogate"));
+        }
         Ok(())
     }
 
-    fn write_object(&mut self, output: &mut Utf16String, obj: JsObject) -> Result<()> {
+    fn write_object(&mut self, obj: JsObject) -> StringifyResult<()> {
+        if let Some(is_raw_json) = &self.is_raw_json {
+            let is_raw: JsBoolean = is_raw_json.call(None, &[obj])?.coerce_to_bool()?;
+            if is_raw.get_value()? {
+                if self.canonical {
+                    return Err(StringifyError::UnsupportedValue("a raw JSON value"));
+                }
+                // `JSON.rawJSON` checked that it is a JSON number, string,
+                // boolean or null, so it is written as is.
+                let json: JsString = obj.get_named_property("rawJSON")?;
+                self.writer.raw(json.into_utf8()?.as_str()?);
+                return Ok(());
+            }
+        }
+
         // Handle BigNumber
         if BigNumber::instance_of(self.env, &obj)? {
+            if self.canonical {
+                return Err(StringifyError::UnsupportedValue("a BigNumber"));
+            }
+            if self.serialize_number(obj.into_unknown())? {
+                return Ok(());
+            }
             let num: &BigNumber =
                 unsafe { FromNapiValue::from_napi_value(self.env.raw(), obj.raw())? };
-            let s = num.to_string();
-            output.push_str(&s);
+            if let Some(value) = num.2 {
+                return self.write_double(value);
+            }
+            let text = self.big_number_text(num);
+            self.writer.number(&text);
             return Ok(());
         }
 
-        // Handle array
-        if obj.is_array()? {
-            let length = obj.get_array_length()?;
-            output.push_utfstr(utf16str!("["));
-            for i in 0..length {
-                if i > 0 {
-                    output.push(',');
-                }
-                let element: JsUnknown = obj.get_element_unchecked(i)?;
-                self.write_value(output, element)?;
+        if LosslessNumber::instance_of(self.env, &obj)? {
+            if self.canonical {
+                return Err(StringifyError::UnsupportedValue("a LosslessNumber"));
+            }
+            if self.serialize_number(obj.into_unknown())? {
+                return Ok(());
             }
-            output.push_utfstr(utf16str!("]"));
+            let num: &LosslessNumber =
+                unsafe { FromNapiValue::from_napi_value(self.env.raw(), obj.raw())? };
+            self.writer.number(&num.0);
             return Ok(());
         }
 
-        // Handle object
-        output.push_utfstr(utf16str!("{"));
+        if obj.is_typedarray()? || obj.is_arraybuffer()? {
+            return self.write_binary(obj);
+        }
+
+        if self.write_ref(obj)? {
+            return Ok(());
+        }
+        self.enter(obj)?;
+        let children = if obj.is_array()? {
+            self.writer.begin_array();
+            Children::Elements(obj.get_array_length()?)
+        } else {
+            self.writer.begin_object();
+            let keys = self.object_keys(obj)?;
+            if self.errors != ErrorFormat::Object
+                && self.allowed_keys.is_none()
+                && obj.is_error()?
+            {
+                Children::Keys(self.error_keys(obj, keys)?)
+            } else {
+                Children::Keys(keys)
+            }
+        };
+        self.stack.push(Frame {
+            obj,
+            children,
+            next: 0,
+        });
+        Ok(())
+    }
+
+    /// `num` in the notation of the options.
+    fn big_number_text(&self, num: &BigNumber) -> String {
+        let scientific = match self.notation {
+            BigNumberNotation::Scientific => true,
+            BigNumberNotation::Plain => false,
+            BigNumberNotation::Auto => {
+                let e = num.exponent();
+                match self.exponential_at {
+                    Some(at) => e.unsigned_abs() >= at as u64,
+                    None => e <= -7 || e >= 21,
+                }
+            }
+        };
+        if scientific {
+            num.to_string()
+        } else {
+            num.0.to_plain_string()
+        }
+    }
+
+    /// The keys of `obj` to write, in order.
+    fn object_keys(&self, obj: JsObject) -> StringifyResult<Vec<JsString>> {
+        if let Some(keys) = &self.allowed_keys {
+            return Ok(keys.clone());
+        }
         let names = obj.get_property_names()?;
+        if let (Some(Either::B(comparator)), false) = (&self.sort_keys, self.canonical) {
+            let sort: JsFunction = names.get_named_property("sort")?;
+            // `Array.prototype.sort` copes with a comparator that is not consistent.
+            sort.call(Some(&names), &[comparator])?;
+        }
         let len = names.get_array_length_unchecked()?;
-        for i in 0..len {
-            if i > 0 {
-                output.push_utfstr(utf16str!(","));
+        let keys = (0..len).map(|i| names.get_element_unchecked(i));
+        let keys: Vec<JsString> = keys.collect::<Result<_>>()?;
+        if !self.canonical && !matches!(self.sort_keys, Some(Either::A(true))) {
+            return Ok(keys);
+        }
+
+        let mut sorted = Vec::with_capacity(keys.len());
+        for key in keys {
+            sorted.push((key.into_utf16()?.as_slice().to_vec(), key));
+        }
+        sorted.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(sorted.into_iter().map(|(_, key)| key).collect())
+    }
+
+    /// The keys to write for an `Error`: `name`, `message`, `stack` as `errors`
+    /// says and `cause`, if it has one, which are usually not enumerable, then
+    /// `keys`, the enumerable ones.
+    fn error_keys(&self, obj: JsObject, keys: Vec<JsString>) -> StringifyResult<Vec<JsString>> {
+        let mut names = vec!["name", "message"];
+        if self.errors == ErrorFormat::Stack {
+            names.push("stack");
+        }
+        if obj.has_own_property("cause")? {
+            names.push("cause");
+        }
+        let mut error_keys = Vec::with_capacity(names.len() + keys.len());
+        for name in &names {
+            error_keys.push(self.env.create_string(name)?);
+        }
+        for key in keys {
+            if !names.contains(&key.into_utf8()?.as_str()?) {
+                error_keys.push(key);
             }
+        }
+        Ok(error_keys)
+    }
 
-            let key: JsString = names.get_element_unchecked(i)?;
-            self.write_string(output, key)?;
+    /// Writes a TypedArray or ArrayBuffer as `binary` says.
+    fn write_binary(&mut self, obj: JsObject) -> StringifyResult<()> {
+        if obj.is_arraybuffer()? {
+            let buffer = unsafe { obj.into_unknown().cast::<JsArrayBuffer>() }.into_value()?;
+            return match self.binary {
+                BinaryFormat::Array => self.write_elements(&buffer[..], write_integer),
+                BinaryFormat::Base64 => {
+                    self.writer.string(base64(&buffer).as_ustr());
+                    Ok(())
+                }
+                BinaryFormat::Error => Err(StringifyError::UnsupportedValue("an ArrayBuffer")),
+            };
+        }
 
-            output.push_utfstr(utf16str!(":"));
+        let array = unsafe { obj.into_unknown().cast::<JsTypedArray>() }.into_value()?;
+        match self.binary {
+            BinaryFormat::Array => {}
+            BinaryFormat::Base64 => {
+                let size = match array.typedarray_type {
+                    TypedArrayType::Int8 | TypedArrayType::Uint8 | TypedArrayType::Uint8Clamped => {
+                        1
+                    }
+                    TypedArrayType::Int16 | TypedArrayType::Uint16 => 2,
+                    TypedArrayType::Int32 | TypedArrayType::Uint32 | TypedArrayType::Float32 => 4,
+                    TypedArrayType::Float64
+                    | TypedArrayType::BigInt64
+                    | TypedArrayType::BigUint64 => 8,
+                    _ => return Err(StringifyError::UnsupportedValue("this TypedArray")),
+                };
+                let start = array.byte_offset;
+                let end = start + array.length * size;
+                let buffer = array.arraybuffer.into_value()?;
+                self.writer.string(base64(&buffer[start..end]).as_ustr());
+                return Ok(());
+            }
+            BinaryFormat::Error => return Err(StringifyError::UnsupportedValue("a TypedArray")),
+        }
 
-            let value: JsUnknown = obj.get_property_unchecked(key)?;
-            self.write_value(output, value)?;
+        match array.typedarray_type {
+            TypedArrayType::Int8 => {
+                self.write_elements(AsRef::<[i8]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Uint8 | TypedArrayType::Uint8Clamped => {
+                self.write_elements(AsRef::<[u8]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Int16 => {
+                self.write_elements(AsRef::<[i16]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Uint16 => {
+                self.write_elements(AsRef::<[u16]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Int32 => {
+                self.write_elements(AsRef::<[i32]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Uint32 => {
+                self.write_elements(AsRef::<[u32]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::Float32 => {
+                let elements = AsRef::<[f32]>::as_ref(&array);
+                self.write_elements(elements, |s, v| s.write_double(v as f64))
+            }
+            TypedArrayType::Float64 => {
+                let elements = AsRef::<[f64]>::as_ref(&array);
+                self.write_elements(elements, |s, v| s.write_double(v))
+            }
+            TypedArrayType::BigInt64 => {
+                self.write_elements(AsRef::<[i64]>::as_ref(&array), write_integer)
+            }
+            TypedArrayType::BigUint64 => {
+                self.write_elements(AsRef::<[u64]>::as_ref(&array), write_integer)
+            }
+            _ => Err(StringifyError::UnsupportedValue("this TypedArray")),
         }
-        output.push_utfstr(utf16str!("}"));
-        Ok(())
     }
 
-    fn write_bigint(&self, output: &mut Utf16String, bigint: JsBigInt) -> Result<()> {
-        let utf16_c = bigint.coerce_to_string()?.into_utf16()?;
-        let (_, utf16) = utf16_c.split_last().unwrap();
-        let s = unsafe { Utf16Str::from_slice_unchecked(utf16) };
-        output.push_utfstr(s);
+    /// Writes `elements` as an array, each one with `write`.
+    fn write_elements<T: Copy>(
+        &mut self,
+        elements: &[T],
+        write: impl Fn(&mut Self, T) -> StringifyResult<()>,
+    ) -> StringifyResult<()> {
+        self.writer.begin_array();
+        for &v in elements {
+            write(self, v)?;
+        }
+        self.writer.end_array();
         Ok(())
     }
+
+    fn write_bigint(&mut self, bigint: JsBigInt) -> StringifyResult<()> {
+        if self.canonical {
+            return Err(StringifyError::UnsupportedValue("a BigInt"));
+        }
+        match self.bigint {
+            BigIntFormat::Number => {
+                let s = bigint.coerce_to_string()?.into_utf8()?;
+                self.writer.number(s.as_str()?);
+                Ok(())
+            }
+            BigIntFormat::String => self.write_string(bigint.coerce_to_string()?),
+            BigIntFormat::Error => Err(StringifyError::UnsupportedValue("a BigInt")),
+        }
+    }
+}
+
+/// `JSON.isRawJSON`, which engines without `JSON.rawJSON` don't have.
+fn is_raw_json_function(env: &Env) -> Option<JsFunction> {
+    let json: JsObject = env.get_global().ok()?.get_named_property("JSON").ok()?;
+    let function: JsUnknown = json.get_named_property("isRawJSON").ok()?;
+    match function.get_type().ok()? {
+        ValueType::Function => Some(unsafe { function.cast() }),
+        _ => None,
+    }
+}
+
+/// Whether `value` has no JSON form, like `undefined`, a function or a symbol.
+fn is_unwritable(value: JsUnknown) -> Result<bool> {
+    Ok(matches!(
+        value.get_type()?,
+        ValueType::Undefined | ValueType::Function | ValueType::Symbol
+    ))
+}
+
+/// Writes an integer element of a TypedArray or ArrayBuffer.
+fn write_integer<W: JsonWriter, T: itoa::Integer>(
+    s: &mut JsonStringifier<'_, W>,
+    v: T,
+) -> StringifyResult<()> {
+    s.writer.number(itoa::Buffer::new().format(v));
+    Ok(())
+}
+
+/// Integers smaller than this in magnitude are written with `itoa`, as every
+/// one of them is exactly an `f64` and an `i64`.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `bytes` as a padded base64 string (RFC 4648).
+fn base64(bytes: &[u8]) -> Utf16String {
+    let mut output = Utf16String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().fold(0u32, |n, &b| n << 8 | b as u32) << (8 * (3 - chunk.len()));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3F;
+                output.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
 }
@@ -0,0 +1,183 @@
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{FnArgs, Function, FunctionRef, JsValuesTupleIntoVec};
+use napi_ohos::{Env, JsString, JsUnknown, Result};
+use widestring::{U16Str, U16String};
+
+use crate::builder::{Builder, JsBuilder, Number};
+use crate::error::{JsonErrorCode, ParseResult, with_code};
+use crate::options::Options;
+use crate::parse::JsonParser;
+use crate::path::PathSegment;
+
+type EventRef = FunctionRef<(), JsUnknown>;
+type KeyRef = FunctionRef<JsString, JsUnknown>;
+type ValueRef = FunctionRef<FnArgs<(JsUnknown, Option<String>)>, JsUnknown>;
+
+/// The callbacks of `tokenize`. Each one is optional and its result is ignored.
+#[napi(object)]
+#[derive(Default)]
+pub struct TokenizeCallbacks {
+    pub start_object: Option<EventRef>,
+    pub end_object: Option<EventRef>,
+    pub start_array: Option<EventRef>,
+    pub end_array: Option<EventRef>,
+    /// `(key: string) => void`, called before the value of the key.
+    pub key: Option<KeyRef>,
+    /// `(value: unknown, raw?: string) => void`, called for every value that is
+    /// not an array or object. `raw` is the text of a number.
+    pub value: Option<ValueRef>,
+}
+
+/// Parses `s` and reports what it finds through `callbacks`, without creating
+/// the arrays and objects.
+#[napi]
+#[allow(dead_code)]
+pub fn tokenize(
+    env: Env,
+    s: String,
+    callbacks: TokenizeCallbacks,
+    options: Option<Options>,
+) -> Result<()> {
+    let opts = options.unwrap_or_default();
+    let builder = EventBuilder::new(&env, &opts, &callbacks)
+        .map_err(|e| with_code(&env, e, JsonErrorCode::Internal))?;
+    let mut parser = JsonParser::new(s.as_bytes(), &opts, builder);
+    parser.parse().map_err(|e| e.into_error(&env))
+}
+
+fn borrow<'a, A: JsValuesTupleIntoVec, R>(
+    env: &'a Env,
+    f: &Option<FunctionRef<A, R>>,
+) -> Result<Option<Function<'a, A, R>>> {
+    f.as_ref().map(|f| f.borrow_back(env)).transpose()
+}
+
+/// Calls the callbacks in document order. Scalars are created by a `JsBuilder`
+/// so the number options apply as in `parse`.
+struct EventBuilder<'a> {
+    scalars: JsBuilder<'a>,
+    start_object: Option<Function<'a, (), JsUnknown>>,
+    end_object: Option<Function<'a, (), JsUnknown>>,
+    start_array: Option<Function<'a, (), JsUnknown>>,
+    end_array: Option<Function<'a, (), JsUnknown>>,
+    key: Option<Function<'a, JsString, JsUnknown>>,
+    value: Option<Function<'a, FnArgs<(JsUnknown, Option<String>)>, JsUnknown>>,
+    env: &'a Env,
+    /// The text of the number being built.
+    raw: Option<String>,
+}
+
+impl<'a> EventBuilder<'a> {
+    fn new(env: &'a Env, opts: &Options, callbacks: &TokenizeCallbacks) -> Result<Self> {
+        Ok(EventBuilder {
+            scalars: JsBuilder::new(env, opts, None)?,
+            start_object: borrow(env, &callbacks.start_object)?,
+            end_object: borrow(env, &callbacks.end_object)?,
+            start_array: borrow(env, &callbacks.start_array)?,
+            end_array: borrow(env, &callbacks.end_array)?,
+            key: borrow(env, &callbacks.key)?,
+            value: borrow(env, &callbacks.value)?,
+            env,
+            raw: None,
+        })
+    }
+
+    fn emit_value(&mut self, value: JsUnknown) -> ParseResult<()> {
+        let raw = self.raw.take();
+        if let Some(f) = &self.value {
+            f.call((value, raw).into())?;
+        }
+        Ok(())
+    }
+}
+
+fn emit(f: &Option<Function<'_, (), JsUnknown>>) -> ParseResult<()> {
+    if let Some(f) = f {
+        f.call(())?;
+    }
+    Ok(())
+}
+
+impl Builder for EventBuilder<'_> {
+    type Value = ();
+    type Array = ();
+    type Object = ();
+
+    fn null(&mut self) -> ParseResult<()> {
+        let value = self.scalars.null()?;
+        self.emit_value(value)
+    }
+
+    fn boolean(&mut self, value: bool) -> ParseResult<()> {
+        let value = self.scalars.boolean(value)?;
+        self.emit_value(value)
+    }
+
+    fn number(&mut self, value: Number) -> ParseResult<()> {
+        let value = self.scalars.number(value)?;
+        self.emit_value(value)
+    }
+
+    fn wants_raw_numbers(&self) -> bool {
+        true
+    }
+
+    fn raw_number(&mut self, raw: &str, path: &[PathSegment]) -> ParseResult<Option<()>> {
+        self.raw = Some(raw.to_owned());
+        match self.scalars.raw_number(raw, path)? {
+            Some(value) => self.emit_value(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn precision_loss(&mut self, raw: &str, path: &[PathSegment]) -> ParseResult<()> {
+        self.scalars.precision_loss(raw, path)
+    }
+
+    fn string(&mut self, value: U16String) -> ParseResult<()> {
+        let value = self.scalars.string(value)?;
+        self.emit_value(value)
+    }
+
+    fn date(&mut self, value: U16String) -> ParseResult<()> {
+        let value = self.scalars.date(value)?;
+        self.emit_value(value)
+    }
+
+    fn ascii_string(&mut self, value: &[u8]) -> ParseResult<()> {
+        let value = self.scalars.ascii_string(value)?;
+        self.emit_value(value)
+    }
+
+    fn begin_array(&mut self) -> ParseResult<()> {
+        emit(&self.start_array)
+    }
+
+    fn push(&mut self, _array: &mut (), _value: ()) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn end_array(&mut self, _array: ()) -> ParseResult<()> {
+        emit(&self.end_array)
+    }
+
+    fn begin_object(&mut self) -> ParseResult<()> {
+        emit(&self.start_object)
+    }
+
+    fn key(&mut self, key: &U16Str) -> ParseResult<()> {
+        if let Some(f) = &self.key {
+            let key = self.env.create_string_utf16(key.as_slice())?;
+            f.call(key)?;
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, _object: &mut (), _key: U16String, _value: ()) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn end_object(&mut self, _object: ()) -> ParseResult<()> {
+        emit(&self.end_object)
+    }
+}
@@ -0,0 +1,111 @@
+use napi_derive_ohos::napi;
+use napi_ohos::bindgen_prelude::{ArrayBuffer, Either3, Uint8Array};
+use widestring::U16String;
+
+use crate::builder::{Builder, Number};
+use crate::error::{JsonErrorCode, ParseResult};
+use crate::options::Options;
+use crate::parse::JsonParser;
+
+#[napi(object)]
+pub struct ErrorPosition {
+    pub offset: u32,
+    pub line: u32,
+    pub column: u32,
+}
+
+#[napi(object)]
+pub struct ValidationResult {
+    pub ok: bool,
+    /// The message `parse` would throw.
+    pub error: Option<String>,
+    pub code: Option<JsonErrorCode>,
+    pub position: Option<ErrorPosition>,
+}
+
+/// Checks that `input` would parse with `options`, without creating any JS values.
+#[napi]
+#[allow(dead_code)]
+pub fn validate(
+    input: Either3<String, ArrayBuffer, Uint8Array>,
+    options: Option<Options>,
+) -> ValidationResult {
+    let bytes: &[u8] = match &input {
+        Either3::A(s) => s.as_bytes(),
+        Either3::B(b) => b,
+        Either3::C(b) => b,
+    };
+    let opts = options.unwrap_or_default();
+    let mut parser = JsonParser::new(bytes, &opts, NullBuilder);
+    match parser.parse() {
+        Ok(()) => ValidationResult {
+            ok: true,
+            error: None,
+            code: None,
+            position: None,
+        },
+        Err(e) => ValidationResult {
+            ok: false,
+            error: Some(e.to_string()),
+            code: Some(e.error.code()),
+            position: Some(ErrorPosition {
+                offset: e.position.offset as u32,
+                line: e.position.line as u32,
+                column: e.position.column as u32,
+            }),
+        },
+    }
+}
+
+/// Discards everything.
+pub struct NullBuilder;
+
+impl Builder for NullBuilder {
+    type Value = ();
+    type Array = ();
+    type Object = ();
+
+    fn null(&mut self) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn boolean(&mut self, _value: bool) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn number(&mut self, _value: Number) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn string(&mut self, _value: U16String) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn date(&mut self, _value: U16String) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn begin_array(&mut self) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn push(&mut self, _array: &mut (), _value: ()) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn end_array(&mut self, _array: ()) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn begin_object(&mut self) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn insert(&mut self, _object: &mut (), _key: U16String, _value: ()) -> ParseResult<()> {
+        Ok(())
+    }
+
+    fn end_object(&mut self, _object: ()) -> ParseResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::vec;
+
+use widestring::U16String;
+
+use crate::builder::{Builder, Number, NumberArray};
+use crate::error::ParseResult;
+
+/// An owned JSON document that can be built without a napi environment.
+#[derive(Debug, Clone)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(U16String),
+    /// A string that `parse_dates` selected.
+    Date(U16String),
+    Array(Vec<JsonValue>),
+    /// An array of numbers, for `typed_arrays`.
+    NumberArray(NumberArray),
+    Object(Vec<(U16String, JsonValue)>),
+}
+
+/// A container whose remaining children are still to be replayed.
+enum Frame<A, O> {
+    Array(A, vec::IntoIter<JsonValue>),
+    Object(O, vec::IntoIter<(U16String, JsonValue)>, U16String),
+}
+
+impl JsonValue {
+    /// Replays the document into another builder, e.g. `JsBuilder` on the JS thread.
+    ///
+    /// Like the parser, this walks the tree with an explicit stack.
+    pub fn build<B: Builder>(self, builder: &mut B) -> ParseResult<B::Value> {
+        let mut stack: Vec<Frame<B::Array, B::Object>> = Vec::new();
+        let mut next = self;
+
+        loop {
+            let mut value = match next {
+                JsonValue::Null => Some(builder.null()?),
+                JsonValue::Bool(v) => Some(builder.boolean(v)?),
+                JsonValue::Number(v) => Some(builder.number(v)?),
+                JsonValue::String(v) => Some(builder.string(v)?),
+                JsonValue::Date(v) => Some(builder.date(v)?),
+                JsonValue::NumberArray(v) => Some(builder.number_array(v)?),
+                JsonValue::Array(elements) => {
+                    let array = builder.begin_array()?;
+                    stack.push(Frame::Array(array, elements.into_iter()));
+                    None
+                }
+                JsonValue::Object(entries) => {
+                    let object = builder.begin_object()?;
+                    stack.push(Frame::Object(object, entries.into_iter(), U16String::new()));
+                    None
+                }
+            };
+
+            loop {
+                let Some(frame) = stack.last_mut() else {
+                    // The stack only empties after the root value has been built.
+                    return builder.finish(value.unwrap());
+                };
+                match frame {
+                    Frame::Array(array, elements) => {
+                        if let Some(v) = value.take() {
+                            builder.push(array, v)?;
+                        }
+                        if let Some(element) = elements.next() {
+                            next = element;
+                            break;
+                        }
+                        let Some(Frame::Array(array, _)) = stack.pop() else {
+                            unreachable!()
+                        };
+                        value = Some(builder.end_array(array)?);
+                    }
+                    Frame::Object(object, entries, key) => {
+                        if let Some(v) = value.take() {
+                            builder.insert(object, std::mem::take(key), v)?;
+                        }
+                        if let Some((k, element)) = entries.next() {
+                            *key = k;
+                            next = element;
+                            break;
+                        }
+                        let Some(Frame::Object(object, _, _)) = stack.pop() else {
+                            unreachable!()
+                        };
+                        value = Some(builder.end_object(object)?);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Builds a `JsonValue` tree.
+pub struct ValueBuilder;
+
+impl Builder for ValueBuilder {
+    type Value = JsonValue;
+    type Array = Vec<JsonValue>;
+    type Object = Vec<(U16String, JsonValue)>;
+
+    fn null(&mut self) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Null)
+    }
+
+    fn boolean(&mut self, value: bool) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Bool(value))
+    }
+
+    fn number(&mut self, value: Number) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Number(value))
+    }
+
+    fn string(&mut self, value: U16String) -> ParseResult<JsonValue> {
+        Ok(JsonValue::String(value))
+    }
+
+    fn date(&mut self, value: U16String) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Date(value))
+    }
+
+    fn wants_number_arrays(&self) -> bool {
+        true
+    }
+
+    fn number_array(&mut self, values: NumberArray) -> ParseResult<JsonValue> {
+        Ok(JsonValue::NumberArray(values))
+    }
+
+    fn begin_array(&mut self) -> ParseResult<Vec<JsonValue>> {
+        Ok(Vec::new())
+    }
+
+    fn push(&mut self, array: &mut Vec<JsonValue>, value: JsonValue) -> ParseResult<()> {
+        array.push(value);
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: Vec<JsonValue>) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Array(array))
+    }
+
+    fn begin_object(&mut self) -> ParseResult<Vec<(U16String, JsonValue)>> {
+        Ok(Vec::new())
+    }
+
+    fn insert(
+        &mut self,
+        object: &mut Vec<(U16String, JsonValue)>,
+        key: U16String,
+        value: JsonValue,
+    ) -> ParseResult<()> {
+        object.push((key, value));
+        Ok(())
+    }
+
+    fn end_object(&mut self, object: Vec<(U16String, JsonValue)>) -> ParseResult<JsonValue> {
+        Ok(JsonValue::Object(object))
+    }
+}
@@ -0,0 +1,364 @@
+use std::cell::Cell;
+use std::fmt::Write;
+
+use widestring::{U16Str, U16String};
+
+use crate::error::{StringifyError, StringifyResult};
+
+/// Receives the values found by `JsonStringifier`, in document order.
+///
+/// Each value of an object is preceded by a call to `key`.
+pub trait JsonWriter {
+    fn null(&mut self);
+    fn boolean(&mut self, value: bool);
+    /// A number already written as JSON, e.g. `1.5` or the digits of a BigInt.
+    fn number(&mut self, text: &str);
+    /// A JS string, which may have unpaired surrogates.
+    fn string(&mut self, value: &U16Str);
+    /// A value already written as JSON, from `JSON.rawJSON`.
+    fn raw(&mut self, json: &str);
+
+    fn begin_array(&mut self);
+    fn end_array(&mut self);
+
+    fn begin_object(&mut self);
+    fn key(&mut self, key: &U16Str);
+    fn end_object(&mut self);
+
+    /// Called after each complete value, so that what has been written so far
+    /// can be passed on.
+    fn flush(&mut self) -> StringifyResult<()> {
+        Ok(())
+    }
+}
+
+/// The capacity a new output buffer starts with.
+const INITIAL_CAPACITY: usize = 1024;
+
+/// The largest output buffer kept for reuse, in bytes.
+const MAX_SPARE_CAPACITY: usize = 8 * 1024 * 1024;
+
+thread_local! {
+    /// The output buffer of the last `TextWriter` dropped on this thread, so
+    /// that a large output doesn't have to grow its buffer from scratch each time.
+    static SPARE_OUTPUT: Cell<String> = const { Cell::new(String::new()) };
+}
+
+/// Writes JSON text.
+pub struct TextWriter {
+    /// The text as UTF-8, which is converted to a JS string once at the end.
+    pub output: String,
+    /// What each level of nesting is indented by, empty for output on one line.
+    gap: String,
+    /// Write `\u` escapes with lowercase hex digits, as RFC 8785 asks.
+    pub lowercase_hex: bool,
+    /// Escape every character that is not ASCII.
+    pub ensure_ascii: bool,
+    /// What goes between the elements of an array or the properties of an object.
+    pub item_separator: String,
+    /// What goes between a key and its value.
+    pub key_separator: String,
+    /// The most bytes of text allowed, counting what was cleared from `output`.
+    /// `flush` fails once there are more.
+    pub max_len: Option<usize>,
+    /// How many bytes were cleared from `output` so far.
+    cleared: usize,
+    /// The indentation of the current level.
+    indent: String,
+    /// How many arrays and objects the next value is in.
+    depth: usize,
+    /// Whether nothing has been written yet in the current array or object.
+    empty: bool,
+    /// Whether a key was just written, so the next value follows its colon.
+    after_key: bool,
+}
+
+impl TextWriter {
+    pub fn new(gap: String) -> Self {
+        let key_separator = if gap.is_empty() { ":" } else { ": " };
+        let mut output = SPARE_OUTPUT.take();
+        output.clear();
+        output.reserve(INITIAL_CAPACITY);
+        TextWriter {
+            output,
+            gap,
+            lowercase_hex: false,
+            ensure_ascii: false,
+            item_separator: String::from(","),
+            key_separator: String::from(key_separator),
+            max_len: None,
+            cleared: 0,
+            indent: String::new(),
+            depth: 0,
+            empty: true,
+            after_key: false,
+        }
+    }
+
+    /// Clears `output` once it has been passed on. It still counts toward `max_len`.
+    pub fn clear(&mut self) {
+        self.cleared += self.output.len();
+        self.output.clear();
+    }
+
+    /// Writes the comma and line break, if any, that go before a value or key.
+    fn separate(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+            return;
+        }
+        if self.depth == 0 {
+            return;
+        }
+        if !self.empty {
+            self.output.push_str(&self.item_separator);
+        }
+        self.empty = false;
+        self.newline();
+    }
+
+    /// Starts a new line at the current indentation, if the output is indented.
+    fn newline(&mut self) {
+        if !self.gap.is_empty() {
+            self.output.push('\n');
+            self.output.push_str(&self.indent);
+        }
+    }
+
+    fn begin(&mut self, bracket: char) {
+        self.separate();
+        self.output.push(bracket);
+        self.indent.push_str(&self.gap);
+        self.depth += 1;
+        self.empty = true;
+    }
+
+    fn end(&mut self, bracket: char) {
+        self.depth -= 1;
+        self.indent.truncate(self.indent.len() - self.gap.len());
+        if !self.empty {
+            self.newline();
+        }
+        self.output.push(bracket);
+        // The array or object it was in now has at least this value.
+        self.empty = false;
+    }
+
+    fn write_escaped(&mut self, value: &U16Str) {
+        let output = &mut self.output;
+        output.reserve(2 + value.len());
+        output.push('"');
+        for c in value.chars() {
+            match c {
+                // Like `JSON.stringify`, an unpaired surrogate is escaped, so the
+                // output is well-formed.
+                Err(e) => push_escape(output, e.unpaired_surrogate(), self.lowercase_hex),
+                Ok('"') => output.push_str(r#"\""#),
+                Ok('\\') => output.push_str(r"\\"),
+                Ok('\n') => output.push_str(r"\n"),
+                Ok('\r') => output.push_str(r"\r"),
+                Ok('\t') => output.push_str(r"\t"),
+                Ok('\u{0008}') => output.push_str(r"\b"),
+                Ok('\u{000C}') => output.push_str(r"\f"),
+                Ok(c @ '\u{0000}'..='\u{001F}') => {
+                    push_escape(output, c as u16, self.lowercase_hex)
+                }
+                Ok(c) if self.ensure_ascii && !c.is_ascii() => {
+                    // A character outside the BMP is escaped as its surrogate pair.
+                    for &mut unit in c.encode_utf16(&mut [0; 2]) {
+                        push_escape(output, unit, self.lowercase_hex);
+                    }
+                }
+                Ok(c) => output.push(c),
+            }
+        }
+        output.push('"');
+    }
+}
+
+/// Writes `unit` as a `\uXXXX` escape.
+fn push_escape(output: &mut String, unit: u16, lowercase: bool) {
+    // Writing to a `String` can't fail.
+    let _ = if lowercase {
+        write!(output, "\\u{unit:04x}")
+    } else {
+        write!(output, "\\u{unit:04X}")
+    };
+}
+
+impl Drop for TextWriter {
+    fn drop(&mut self) {
+        let capacity = self.output.capacity();
+        if (INITIAL_CAPACITY..=MAX_SPARE_CAPACITY).contains(&capacity) {
+            SPARE_OUTPUT.set(std::mem::take(&mut self.output));
+        }
+    }
+}
+
+impl JsonWriter for TextWriter {
+    fn null(&mut self) {
+        self.separate();
+        self.output.push_str("null");
+    }
+
+    fn boolean(&mut self, value: bool) {
+        self.separate();
+        self.output.push_str(if value { "true" } else { "false" });
+    }
+
+    fn number(&mut self, text: &str) {
+        self.separate();
+        self.output.push_str(text);
+    }
+
+    fn string(&mut self, value: &U16Str) {
+        self.separate();
+        self.write_escaped(value);
+    }
+
+    fn raw(&mut self, json: &str) {
+        self.separate();
+        self.output.push_str(json);
+    }
+
+    fn begin_array(&mut self) {
+        self.begin('[');
+    }
+
+    fn end_array(&mut self) {
+        self.end(']');
+    }
+
+    fn begin_object(&mut self) {
+        self.begin('{');
+    }
+
+    fn key(&mut self, key: &U16Str) {
+        self.separate();
+        self.write_escaped(key);
+        self.output.push_str(&self.key_separator);
+        self.after_key = true;
+    }
+
+    fn end_object(&mut self) {
+        self.end('}');
+    }
+
+    fn flush(&mut self) -> StringifyResult<()> {
+        match self.max_len {
+            Some(max) if self.cleared + self.output.len() > max => {
+                Err(StringifyError::OutputTooLarge(max))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A call to a `JsonWriter`, recorded so that it can be replayed without a
+/// napi environment, e.g. on a worker thread.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Null,
+    Boolean(bool),
+    Number(String),
+    String(U16String),
+    Raw(String),
+    BeginArray,
+    EndArray,
+    BeginObject,
+    Key(U16String),
+    EndObject,
+}
+
+/// Records the calls made to it as `Event`s.
+#[derive(Default)]
+pub struct Recorder {
+    pub events: Vec<Event>,
+    /// The most bytes of text allowed, as for `TextWriter`. `flush` fails once
+    /// `len` is past it, so a huge value is not recorded whole first.
+    pub max_len: Option<usize>,
+    /// At most the length of the text the events will make, in bytes. Strings
+    /// are counted in UTF-16 code units and separators are left out.
+    len: usize,
+}
+
+impl Recorder {
+    pub fn new(max_len: Option<usize>) -> Self {
+        Recorder {
+            max_len,
+            ..Default::default()
+        }
+    }
+
+    fn push(&mut self, event: Event, len: usize) {
+        self.events.push(event);
+        self.len += len;
+    }
+}
+
+impl JsonWriter for Recorder {
+    fn null(&mut self) {
+        self.push(Event::Null, 4);
+    }
+
+    fn boolean(&mut self, value: bool) {
+        self.push(Event::Boolean(value), if value { 4 } else { 5 });
+    }
+
+    fn number(&mut self, text: &str) {
+        self.push(Event::Number(text.to_owned()), text.len());
+    }
+
+    fn string(&mut self, value: &U16Str) {
+        self.push(Event::String(value.to_owned()), value.len() + 2);
+    }
+
+    fn raw(&mut self, json: &str) {
+        self.push(Event::Raw(json.to_owned()), json.len());
+    }
+
+    fn begin_array(&mut self) {
+        self.push(Event::BeginArray, 1);
+    }
+
+    fn end_array(&mut self) {
+        self.push(Event::EndArray, 1);
+    }
+
+    fn begin_object(&mut self) {
+        self.push(Event::BeginObject, 1);
+    }
+
+    fn key(&mut self, key: &U16Str) {
+        self.push(Event::Key(key.to_owned()), key.len() + 2);
+    }
+
+    fn end_object(&mut self) {
+        self.push(Event::EndObject, 1);
+    }
+
+    fn flush(&mut self) -> StringifyResult<()> {
+        match self.max_len {
+            Some(max) if self.len > max => Err(StringifyError::OutputTooLarge(max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Event {
+    /// Makes the recorded call on `writer`.
+    pub fn replay<W: JsonWriter>(&self, writer: &mut W) {
+        match self {
+            Event::Null => writer.null(),
+            Event::Boolean(v) => writer.boolean(*v),
+            Event::Number(text) => writer.number(text),
+            Event::String(s) => writer.string(s),
+            Event::Raw(json) => writer.raw(json),
+            Event::BeginArray => writer.begin_array(),
+            Event::EndArray => writer.end_array(),
+            Event::BeginObject => writer.begin_object(),
+            Event::Key(key) => writer.key(key),
+            Event::EndObject => writer.end_object(),
+        }
+    }
+}